@@ -4,19 +4,23 @@ use self::r#type::BsaType;
 use crate::client::{
     constants::{
         datetime::{deserialize_with_tz, Date, DateTime, Time},
+        endpoint::Endpoint,
         PUBLIC_KEY,
     },
+    request::get_json,
     serde_helpers::extract_cdata_section,
 };
 use anyhow::Result;
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Bsa {
     #[serde(rename = "@id")]
     pub id: Option<String>,
     pub station: String,
+    #[cfg_attr(feature = "graphql", graphql(name = "type"))]
     pub r#type: Option<BsaType>,
     #[serde(deserialize_with = "extract_cdata_section")]
     pub description: String,
@@ -27,6 +31,8 @@ pub struct Bsa {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct BsaResponse {
     pub date: Date,
     #[serde(deserialize_with = "deserialize_with_tz")]
@@ -49,10 +55,21 @@ pub fn url<T: AsRef<str>>(key: Option<T>) -> String {
 }
 
 pub async fn call<T: AsRef<str>>(key: Option<T>) -> Result<BsaResponse> {
-    let root = reqwest::get(&url(key)).await?.json::<Root>().await?;
+    let root = get_json::<Root, _>(Endpoint::Bsa, url(key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::BsaResponse;
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(key: Option<T>) -> Result<BsaResponse> {
+        block_on(super::call(key))
+    }
+}
+
 #[tokio::test]
 async fn bsa() {
     let response = call::<&str>(None).await.unwrap();
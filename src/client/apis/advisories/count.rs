@@ -1,15 +1,18 @@
 use crate::client::{
     constants::{
         datetime::{deserialize_with_tz, Date, Time},
+        endpoint::Endpoint,
         PUBLIC_KEY,
     },
+    request::get_json,
     serde_helpers::from_str,
 };
 use anyhow::Result;
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Count {
     pub date: Date,
     #[serde(deserialize_with = "deserialize_with_tz")]
@@ -33,10 +36,21 @@ pub fn url<T: AsRef<str>>(key: Option<T>) -> String {
 }
 
 pub async fn call<T: AsRef<str>>(key: Option<T>) -> Result<Count> {
-    let root = reqwest::get(&url(key)).await?.json::<Root>().await?;
+    let root = get_json::<Root, _>(Endpoint::Count, url(key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::Count;
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(key: Option<T>) -> Result<Count> {
+        block_on(super::call(key))
+    }
+}
+
 #[tokio::test]
 async fn count() {
     let response = call::<&str>(None).await.unwrap();
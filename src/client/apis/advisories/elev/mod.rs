@@ -4,19 +4,23 @@ use self::r#type::ElevType;
 use crate::client::{
     constants::{
         datetime::{deserialize_with_tz, Date, DateTime, Time},
+        endpoint::Endpoint,
         PUBLIC_KEY,
     },
+    request::get_json,
     serde_helpers::{deserialize_option, extract_cdata_section},
 };
 use anyhow::Result;
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Elev {
     #[serde(rename = "@id")]
     pub id: Option<String>,
     pub station: String,
+    #[cfg_attr(feature = "graphql", graphql(name = "type"))]
     pub r#type: Option<ElevType>,
     #[serde(deserialize_with = "extract_cdata_section")]
     pub description: String,
@@ -29,6 +33,8 @@ pub struct Elev {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct ElevResponse {
     pub date: Date,
     #[serde(deserialize_with = "deserialize_with_tz")]
@@ -51,10 +57,21 @@ pub fn url<T: AsRef<str>>(key: Option<T>) -> String {
 }
 
 pub async fn call<T: AsRef<str>>(key: Option<T>) -> Result<ElevResponse> {
-    let root = reqwest::get(&url(key)).await?.json::<Root>().await?;
+    let root = get_json::<Root, _>(Endpoint::Elev, url(key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::ElevResponse;
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(key: Option<T>) -> Result<ElevResponse> {
+        block_on(super::call(key))
+    }
+}
+
 #[tokio::test]
 async fn elev() {
     let response = call::<&str>(None).await.unwrap();
@@ -6,32 +6,37 @@ use crate::client::{
         color::Color,
         datetime::{deserialize_with_tz, Date, Time},
         direction::Direction,
+        endpoint::Endpoint,
         station::Station,
         PUBLIC_KEY,
     },
+    request::get_json,
     serde_helpers::{bool_from_number_str, from_str},
 };
-use anyhow::Result;
-use reqwest;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct EtdEstimate {
-    minutes: EtdEstimateMinutes,
+    pub minutes: EtdEstimateMinutes,
     #[serde(deserialize_with = "from_str")]
-    platform: i32,
-    direction: Direction,
+    pub platform: i32,
+    pub direction: Direction,
     #[serde(deserialize_with = "from_str")]
-    length: i32,
-    color: Color,
-    hexcolor: Color,
+    pub length: i32,
+    pub color: Color,
+    pub hexcolor: Color,
     #[serde(deserialize_with = "bool_from_number_str")]
-    bikeflag: bool,
+    pub bikeflag: bool,
     #[serde(deserialize_with = "from_str")]
-    delay: i32,
+    pub delay: i32,
 }
 
+// Exposed to GraphQL by `server::graphql::objects`
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Etd {
     // Does not always exactly match an acutal station name (e.g. "Warm Springs" instead of "Warm
     // Springs/South Fremont")
@@ -43,6 +48,9 @@ pub struct Etd {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(context = crate::server::graphql::Context))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct EtdStation {
     // Does not always exactly match an acutal station name (e.g. "Warm Springs" instead of "Warm
     // Springs/South Fremont")
@@ -52,6 +60,9 @@ pub struct EtdStation {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(context = crate::server::graphql::Context))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct EtdResponse {
     pub date: Date,
     #[serde(deserialize_with = "deserialize_with_tz")]
@@ -65,7 +76,7 @@ struct Root {
     pub root: EtdResponse,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum EtdOptionsPlatform {
     One,
     Two,
@@ -73,18 +84,74 @@ pub enum EtdOptionsPlatform {
     Four,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl EtdOptionsPlatform {
+    pub fn from_number(number: u8) -> Result<EtdOptionsPlatform> {
+        match number {
+            1 => Ok(EtdOptionsPlatform::One),
+            2 => Ok(EtdOptionsPlatform::Two),
+            3 => Ok(EtdOptionsPlatform::Three),
+            4 => Ok(EtdOptionsPlatform::Four),
+            _ => Err(anyhow!("Does not match any platform")),
+        }
+    }
+
+    pub fn to_number(&self) -> u8 {
+        match self {
+            EtdOptionsPlatform::One => 1,
+            EtdOptionsPlatform::Two => 2,
+            EtdOptionsPlatform::Three => 3,
+            EtdOptionsPlatform::Four => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum EtdOptionsDirectionOrPlatform {
     Direction(Direction),
     Platform(EtdOptionsPlatform),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum EtdOptions {
     OriginAll,
+    Origin(Station),
     OriginAndDirectionOrPlatform(Station, EtdOptionsDirectionOrPlatform),
 }
 
+impl EtdOptions {
+    // `None` when departures from every station are wanted
+    pub fn station(&self) -> Option<&Station> {
+        match self {
+            EtdOptions::OriginAll => None,
+            EtdOptions::Origin(station) | EtdOptions::OriginAndDirectionOrPlatform(station, _) => {
+                Some(station)
+            }
+        }
+    }
+
+    // BART only accepts one of direction or platform, and neither of them without an origin
+    pub fn from_parts(
+        station: Option<Station>,
+        direction: Option<Direction>,
+        platform: Option<EtdOptionsPlatform>,
+    ) -> Result<EtdOptions> {
+        match (station, direction, platform) {
+            (None, None, None) => Ok(EtdOptions::OriginAll),
+            (None, _, _) => Err(anyhow!("Direction and platform require a station")),
+            (Some(_), Some(_), Some(_)) => Err(anyhow!("Use either direction or platform")),
+            (Some(station), None, None) => Ok(EtdOptions::Origin(station)),
+            (Some(station), Some(direction), None) => Ok(EtdOptions::OriginAndDirectionOrPlatform(
+                station,
+                EtdOptionsDirectionOrPlatform::Direction(direction),
+            )),
+            (Some(station), None, Some(platform)) => Ok(EtdOptions::OriginAndDirectionOrPlatform(
+                station,
+                EtdOptionsDirectionOrPlatform::Platform(platform),
+            )),
+        }
+    }
+}
+
 const URL_ROOT: &str = "https://api.bart.gov/api/etd.aspx?cmd=etd&json=y";
 pub fn url<T: AsRef<str>>(options: &EtdOptions, key: Option<T>) -> String {
     let url_with_key = format!(
@@ -95,6 +162,7 @@ pub fn url<T: AsRef<str>>(options: &EtdOptions, key: Option<T>) -> String {
     );
     match options {
         EtdOptions::OriginAll => format!("{}&orig=ALL", url_with_key),
+        EtdOptions::Origin(station) => format!("{}&orig={}", url_with_key, station.to_abbr()),
         EtdOptions::OriginAndDirectionOrPlatform(station, direction_or_platform) => {
             let url_with_key_and_orig = format!("{}&orig={}", url_with_key, station.to_abbr());
             match direction_or_platform {
@@ -102,13 +170,7 @@ pub fn url<T: AsRef<str>>(options: &EtdOptions, key: Option<T>) -> String {
                     format!("{}&dir={}", url_with_key_and_orig, direction.to_code())
                 }
                 EtdOptionsDirectionOrPlatform::Platform(platform) => {
-                    let platform_number = match platform {
-                        EtdOptionsPlatform::One => 1,
-                        EtdOptionsPlatform::Two => 2,
-                        EtdOptionsPlatform::Three => 3,
-                        EtdOptionsPlatform::Four => 4,
-                    };
-                    format!("{}&plat={}", url_with_key_and_orig, platform_number)
+                    format!("{}&plat={}", url_with_key_and_orig, platform.to_number())
                 }
             }
         }
@@ -116,13 +178,21 @@ pub fn url<T: AsRef<str>>(options: &EtdOptions, key: Option<T>) -> String {
 }
 
 pub async fn call<T: AsRef<str>>(options: &EtdOptions, key: Option<T>) -> Result<EtdResponse> {
-    let root = reqwest::get(&url(options, key))
-        .await?
-        .json::<Root>()
-        .await?;
+    let root = get_json::<Root, _>(Endpoint::Etd, url(options, key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::{EtdOptions, EtdResponse};
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(options: &EtdOptions, key: Option<T>) -> Result<EtdResponse> {
+        block_on(super::call(options, key))
+    }
+}
+
 #[tokio::test]
 async fn etd() {
     let etd_response = call::<&str>(&EtdOptions::OriginAll, None).await.unwrap();
@@ -1,18 +1,24 @@
 use crate::client::{
-    constants::{color::Color, station::Station, PUBLIC_KEY},
+    constants::{color::Color, endpoint::Endpoint, station::Station, PUBLIC_KEY},
+    request::get_json,
     serde_helpers::{bool_from_number_str, from_str},
 };
 use anyhow::Result;
 use chrono::NaiveDate;
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct RouteConfig {
     pub station: Vec<Station>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "RouteInfo"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "RouteInfo"))]
 pub struct Route {
     pub name: String, // Should be an enum
     pub abbr: String, // Should be an enum
@@ -32,11 +38,17 @@ pub struct Route {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "RouteInfoRoutes"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "RouteInfoRoutes"))]
 pub struct Routes {
     pub route: Route,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct RouteInfoResponse {
     #[serde(deserialize_with = "from_str")]
     pub sched_num: i32,
@@ -96,13 +108,25 @@ pub async fn call<T: AsRef<str>>(
     options: &Option<RouteInfoOptions>,
     key: Option<T>,
 ) -> Result<RouteInfoResponse> {
-    let root = reqwest::get(&url(route, options, key))
-        .await?
-        .json::<Root>()
-        .await?;
+    let root = get_json::<Root, _>(Endpoint::RouteInfo, url(route, options, key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::{RouteInfoOptions, RouteInfoResponse};
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(
+        route: u8,
+        options: &Option<RouteInfoOptions>,
+        key: Option<T>,
+    ) -> Result<RouteInfoResponse> {
+        block_on(super::call(route, options, key))
+    }
+}
+
 #[tokio::test]
 async fn routeinfo() {
     let response = call::<&str>(1, &None, None).await.unwrap();
@@ -1,13 +1,15 @@
 use crate::client::{
-    constants::{color::Color, PUBLIC_KEY},
+    constants::{color::Color, endpoint::Endpoint, PUBLIC_KEY},
+    request::get_json,
     serde_helpers::from_str,
 };
 use anyhow::Result;
 use chrono::NaiveDate;
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Route {
     pub name: String, // Should be an enum
     pub abbr: String, // Should be an enum
@@ -20,11 +22,15 @@ pub struct Route {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Routes {
     pub route: Vec<Route>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct RoutesResponse {
     #[serde(deserialize_with = "from_str")]
     pub sched_num: i32,
@@ -78,13 +84,24 @@ pub async fn call<T: AsRef<str>>(
     options: &Option<RoutesOptions>,
     key: Option<T>,
 ) -> Result<RoutesResponse> {
-    let root = reqwest::get(&url(options, key))
-        .await?
-        .json::<Root>()
-        .await?;
+    let root = get_json::<Root, _>(Endpoint::Routes, url(options, key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::{RoutesOptions, RoutesResponse};
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(
+        options: &Option<RoutesOptions>,
+        key: Option<T>,
+    ) -> Result<RoutesResponse> {
+        block_on(super::call(options, key))
+    }
+}
+
 #[tokio::test]
 async fn routes() {
     let response = call::<&str>(&None, None).await.unwrap();
@@ -1,46 +1,51 @@
 use crate::client::{
     constants::{
         datetime::{deserialize_without_tz, Date, Time},
+        endpoint::Endpoint,
         fare_type::FareType,
         station::Station,
         PUBLIC_KEY,
     },
+    request::get_json,
     serde_helpers::{bool_from_number_str, from_str},
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::{NaiveDate, NaiveTime};
-use reqwest;
 use serde::{Deserialize, Serialize};
 
+// Exposed to GraphQL by `server::graphql::objects`
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Leg {
     #[serde(rename = "@order", deserialize_with = "from_str")]
-    order: i32,
+    pub order: i32,
     #[serde(rename = "@origin")]
-    origin: Station,
+    pub origin: Station,
     #[serde(rename = "@destination")]
-    destination: Station,
+    pub destination: Station,
     #[serde(rename = "@origTimeMin", deserialize_with = "deserialize_without_tz")]
-    orig_time_min: Time,
+    pub orig_time_min: Time,
     #[serde(rename = "@origTimeDate")]
-    orig_time_date: Date,
+    pub orig_time_date: Date,
     #[serde(rename = "@destTimeMin", deserialize_with = "deserialize_without_tz")]
-    dest_time_min: Time,
+    pub dest_time_min: Time,
     #[serde(rename = "@destTimeDate")]
-    dest_time_date: Date,
+    pub dest_time_date: Date,
     #[serde(rename = "@line")]
-    line: String, // Should be an enum
+    pub line: String, // Should be an enum
     #[serde(rename = "@bikeflag", deserialize_with = "bool_from_number_str")]
-    bikeflag: bool,
+    pub bikeflag: bool,
     // Does not always exactly match an acutal station name (e.g. "Warm Springs" instead of "Warm
     // Springs/South Fremont")
     #[serde(rename = "@trainHeadStation")]
-    train_head_station: String,
+    pub train_head_station: String,
     #[serde(rename = "@load", deserialize_with = "from_str")]
-    load: i32,
+    pub load: i32,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Fare {
     #[serde(rename = "@amount")]
     amount: String,
@@ -51,6 +56,8 @@ pub struct Fare {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Fares {
     #[serde(rename = "@level")]
     level: String,
@@ -58,6 +65,9 @@ pub struct Fares {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(context = crate::server::graphql::Context))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Trip {
     #[serde(rename = "@origin")]
     pub origin: Station,
@@ -78,11 +88,21 @@ pub struct Trip {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(context = crate::server::graphql::Context))]
+#[cfg_attr(feature = "graphql", graphql(name = "TripRequest"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "TripRequest"))]
 pub struct Request {
     pub trip: Vec<Trip>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(context = crate::server::graphql::Context))]
+#[cfg_attr(feature = "graphql", graphql(name = "TripSchedule"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "TripSchedule"))]
 pub struct Schedule {
     pub date: Date,
     #[serde(deserialize_with = "deserialize_without_tz")]
@@ -95,11 +115,18 @@ pub struct Schedule {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "TripMessage"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "TripMessage"))]
 pub struct Message {
     pub legend: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(context = crate::server::graphql::Context))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct ArriveResponse {
     pub origin: Station,
     pub destination: Station,
@@ -134,6 +161,31 @@ pub enum ArriveTripsOptions {
 }
 
 impl ArriveTripsOptions {
+    pub fn from_a_b(a: u8, b: u8) -> Result<ArriveTripsOptions> {
+        match (a, b) {
+            (0, 1) => Ok(ArriveTripsOptions::ZeroBeforeOneAfter),
+            (0, 2) => Ok(ArriveTripsOptions::ZeroBeforeTwoAfter),
+            (0, 3) => Ok(ArriveTripsOptions::ZeroBeforeThreeAfter),
+            (0, 4) => Ok(ArriveTripsOptions::ZeroBeforeFourAfter),
+            (1, 1) => Ok(ArriveTripsOptions::OneBeforeOneAfter),
+            (1, 2) => Ok(ArriveTripsOptions::OneBeforeTwoAfter),
+            (1, 3) => Ok(ArriveTripsOptions::OneBeforeThreeAfter),
+            (1, 4) => Ok(ArriveTripsOptions::OneBeforeFourAfter),
+            (2, 1) => Ok(ArriveTripsOptions::TwoBeforeOneAfter),
+            (2, 2) => Ok(ArriveTripsOptions::TwoBeforeTwoAfter),
+            (2, 3) => Ok(ArriveTripsOptions::TwoBeforeThreeAfter),
+            (2, 4) => Ok(ArriveTripsOptions::TwoBeforeFourAfter),
+            (3, 1) => Ok(ArriveTripsOptions::ThreeBeforeOneAfter),
+            (3, 2) => Ok(ArriveTripsOptions::ThreeBeforeTwoAfter),
+            (3, 3) => Ok(ArriveTripsOptions::ThreeBeforeThreeAfter),
+            (4, 1) => Ok(ArriveTripsOptions::FourBeforeOneAfter),
+            (4, 2) => Ok(ArriveTripsOptions::FourBeforeTwoAfter),
+            _ => Err(anyhow!(
+                "Does not match any combination of trips before and after"
+            )),
+        }
+    }
+
     pub fn as_a_b(&self) -> (u8, u8) {
         match self {
             ArriveTripsOptions::ZeroBeforeOneAfter => (0, 1),
@@ -203,13 +255,21 @@ pub async fn call<T: AsRef<str>>(
     options: &ArriveOptions,
     key: Option<T>,
 ) -> Result<ArriveResponse> {
-    let root = reqwest::get(&url(options, key))
-        .await?
-        .json::<Root>()
-        .await?;
+    let root = get_json::<Root, _>(Endpoint::Arrive, url(options, key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::{ArriveOptions, ArriveResponse};
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(options: &ArriveOptions, key: Option<T>) -> Result<ArriveResponse> {
+        block_on(super::call(options, key))
+    }
+}
+
 #[tokio::test]
 async fn arrive() {
     let arrive_response = call::<&str>(
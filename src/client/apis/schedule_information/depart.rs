@@ -0,0 +1,99 @@
+use super::arrive::{ArriveTripsOptions, Message, Schedule};
+use crate::client::{
+    constants::{endpoint::Endpoint, station::Station, PUBLIC_KEY},
+    request::get_json,
+};
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+// Departures share the same trip shape as arrivals
+pub type DepartTripsOptions = ArriveTripsOptions;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(context = crate::server::graphql::Context))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct DepartResponse {
+    pub origin: Station,
+    pub destination: Station,
+    pub schedule: Schedule,
+    pub message: Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Root {
+    pub root: DepartResponse,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepartOptions {
+    pub orig: Station,
+    pub dest: Station,
+    pub time: Option<NaiveTime>,
+    pub date: Option<NaiveDate>,
+    pub trips: Option<DepartTripsOptions>,
+}
+
+pub fn url<T: AsRef<str>>(options: &DepartOptions, key: Option<T>) -> String {
+    let key = key
+        .map(|k| String::from(k.as_ref()))
+        .unwrap_or_else(|| String::from(PUBLIC_KEY));
+    let orig = options.orig.to_abbr();
+    let dest = options.dest.to_abbr();
+    let time = options
+        .time
+        .map(|naive_time| naive_time.format("%I:%M+%P").to_string())
+        .unwrap_or_else(|| String::from("now"));
+    let date = options
+        .date
+        .map(|naive_date| naive_date.format("%m/%d/%Y").to_string())
+        .unwrap_or_else(|| String::from("today"));
+    let (a, b) = options.trips.clone().unwrap_or_default().as_a_b();
+    format!(
+        "https://api.bart.gov/api/sched.aspx?cmd=depart&json=y&l=1&key={}&orig={}&dest={}&time={}&date={}&a={}&b={}",
+        key,
+        orig,
+        dest,
+        time,
+        date,
+        a,
+        b,
+    )
+}
+
+pub async fn call<T: AsRef<str>>(
+    options: &DepartOptions,
+    key: Option<T>,
+) -> Result<DepartResponse> {
+    let root = get_json::<Root, _>(Endpoint::Depart, url(options, key)).await?;
+    Ok(root.root)
+}
+
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::{DepartOptions, DepartResponse};
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(options: &DepartOptions, key: Option<T>) -> Result<DepartResponse> {
+        block_on(super::call(options, key))
+    }
+}
+
+#[tokio::test]
+async fn depart() {
+    let depart_response = call::<&str>(
+        &DepartOptions {
+            orig: Station::Orinda,
+            dest: Station::Embarcadero,
+            time: None,
+            date: None,
+            trips: None,
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(depart_response.origin, Station::Orinda);
+}
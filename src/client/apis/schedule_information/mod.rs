@@ -1 +1,2 @@
 pub mod arrive;
+pub mod depart;
@@ -1,9 +1,11 @@
 use crate::client::{
-    constants::{datetime::Time, station::Station as StationConstant, PUBLIC_KEY},
+    constants::{
+        datetime::Time, endpoint::Endpoint, station::Station as StationConstant, PUBLIC_KEY,
+    },
+    request::get_json,
     serde_helpers::{bool_from_number_str, extract_cdata_section},
 };
 use anyhow::Result;
-use reqwest;
 use serde::{Deserialize, Deserializer, Serialize};
 use url::Url;
 
@@ -21,6 +23,10 @@ where
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationAccess"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationAccess"))]
 pub struct Station {
     #[serde(rename = "@parking_flag", deserialize_with = "bool_from_number_str")]
     pub parking_flag: bool,
@@ -53,20 +59,33 @@ pub struct Station {
     pub destinations: String,
     #[serde(deserialize_with = "extract_cdata_section")]
     pub transit_info: String,
+    #[cfg_attr(feature = "openapi", schemars(with = "String"))]
     link: Url,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationAccessStations"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationAccessStations"))]
 pub struct Stations {
     pub station: Station,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationAccessMessage"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationAccessMessage"))]
 pub struct Message {
     pub legend: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationAccessResponse"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationAccessResponse"))]
 pub struct StationsResponse {
     pub stations: Stations,
     pub message: Message,
@@ -90,10 +109,21 @@ pub async fn call<T: AsRef<str>>(
     orig: StationConstant,
     key: Option<T>,
 ) -> Result<StationsResponse> {
-    let root = reqwest::get(&url(orig, key)).await?.json::<Root>().await?;
+    let root = get_json::<Root, _>(Endpoint::StnAccess, url(orig, key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::{StationConstant, StationsResponse};
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(orig: StationConstant, key: Option<T>) -> Result<StationsResponse> {
+        block_on(super::call(orig, key))
+    }
+}
+
 #[tokio::test]
 async fn stnaccess() {
     let response = call::<&str>(StationConstant::Orinda, None).await.unwrap();
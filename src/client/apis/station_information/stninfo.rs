@@ -1,23 +1,35 @@
 use crate::client::{
-    constants::{station::Station as StationConstant, PUBLIC_KEY},
+    constants::{endpoint::Endpoint, station::Station as StationConstant, PUBLIC_KEY},
+    request::get_json,
     serde_helpers::extract_cdata_section,
 };
 use anyhow::Result;
-use reqwest;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationInfoRoutes"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationInfoRoutes"))]
 pub struct Routes {
     pub route: Vec<String>, // Should be an enum of Routes
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationInfoPlatforms"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationInfoPlatforms"))]
 pub struct Platforms {
     pub platform: Vec<String>, // Should be an enum of Platform
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationInfo"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationInfo"))]
 pub struct Station {
     pub name: String,
     pub abbr: StationConstant,
@@ -44,15 +56,24 @@ pub struct Station {
     #[serde(deserialize_with = "extract_cdata_section")]
     pub attraction: String,
     #[serde(deserialize_with = "extract_cdata_section")]
+    #[cfg_attr(feature = "openapi", schemars(with = "String"))]
     link: Url,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationInfoStations"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationInfoStations"))]
 pub struct Stations {
     pub station: Station,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "StationInfoResponse"))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", schemars(rename = "StationInfoResponse"))]
 pub struct StationsResponse {
     pub stations: Stations,
     pub message: String,
@@ -76,10 +97,21 @@ pub async fn call<T: AsRef<str>>(
     orig: StationConstant,
     key: Option<T>,
 ) -> Result<StationsResponse> {
-    let root = reqwest::get(&url(orig, key)).await?.json::<Root>().await?;
+    let root = get_json::<Root, _>(Endpoint::StnInfo, url(orig, key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::{StationConstant, StationsResponse};
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(orig: StationConstant, key: Option<T>) -> Result<StationsResponse> {
+        block_on(super::call(orig, key))
+    }
+}
+
 #[tokio::test]
 async fn stninfo() {
     let response = call::<&str>(StationConstant::MacArthur, None)
@@ -1,9 +1,13 @@
-use crate::client::constants::{station::Station as StationConstant, PUBLIC_KEY};
+use crate::client::{
+    constants::{endpoint::Endpoint, station::Station as StationConstant, PUBLIC_KEY},
+    request::get_json,
+};
 use anyhow::Result;
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Station {
     pub name: String,
     pub abbr: StationConstant,
@@ -17,11 +21,15 @@ pub struct Station {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct Stations {
     pub station: Vec<Station>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct StationsResponse {
     pub stations: Stations,
     pub message: String,
@@ -41,10 +49,21 @@ pub fn url<T: AsRef<str>>(key: Option<T>) -> String {
 }
 
 pub async fn call<T: AsRef<str>>(key: Option<T>) -> Result<StationsResponse> {
-    let root = reqwest::get(&url(key)).await?.json::<Root>().await?;
+    let root = get_json::<Root, _>(Endpoint::Stns, url(key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::StationsResponse;
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(key: Option<T>) -> Result<StationsResponse> {
+        block_on(super::call(key))
+    }
+}
+
 #[tokio::test]
 async fn stns() {
     let response = call::<&str>(None).await.unwrap();
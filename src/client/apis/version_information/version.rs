@@ -1,9 +1,13 @@
-use crate::client::constants::PUBLIC_KEY;
+use crate::client::{
+    constants::{endpoint::Endpoint, PUBLIC_KEY},
+    request::get_json,
+};
 use anyhow::Result;
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Version {
     pub api_version: String,
@@ -26,10 +30,21 @@ pub fn url<T: AsRef<str>>(key: Option<T>) -> String {
 }
 
 pub async fn call<T: AsRef<str>>(key: Option<T>) -> Result<Version> {
-    let root = reqwest::get(&url(key)).await?.json::<Root>().await?;
+    let root = get_json::<Root, _>(Endpoint::Version, url(key)).await?;
     Ok(root.root)
 }
 
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::Version;
+    use crate::client::blocking::block_on;
+    use anyhow::Result;
+
+    pub fn call<T: AsRef<str>>(key: Option<T>) -> Result<Version> {
+        block_on(super::call(key))
+    }
+}
+
 #[tokio::test]
 async fn version() {
     assert_eq!(
@@ -0,0 +1,51 @@
+use lazy_static::lazy_static;
+use std::{cell::Cell, future::Future};
+use tokio::runtime::Runtime;
+
+lazy_static! {
+    // Shared by every blocking call so that pooled connections, the cache and the rate limiter
+    // keep working between calls
+    static ref RUNTIME: Runtime = Runtime::new().expect("Could not start the blocking runtime");
+}
+
+thread_local! {
+    static IN_BLOCKING_CALL: Cell<bool> = Cell::new(false);
+}
+
+// Blocking calls run on a runtime of their own, so anything tied to a runtime, i.e. the HTTP
+// client's connections and calls in flight, has to be kept apart from the async calls' state. The
+// cache and the rate limiter only hold plain data, and stay shared.
+pub fn in_blocking_call() -> bool {
+    IN_BLOCKING_CALL.with(Cell::get)
+}
+
+struct BlockingCall;
+
+impl BlockingCall {
+    fn enter() -> BlockingCall {
+        IN_BLOCKING_CALL.with(|flag| flag.set(true));
+        BlockingCall
+    }
+}
+
+impl Drop for BlockingCall {
+    fn drop(&mut self) {
+        IN_BLOCKING_CALL.with(|flag| flag.set(false));
+    }
+}
+
+// Runs an endpoint call to completion on the current thread. This panics if it is called from
+// within an async context, use the `async` endpoint functions there instead.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let _call = BlockingCall::enter();
+    RUNTIME.block_on(future)
+}
+
+#[test]
+fn blocking() {
+    use crate::client::apis::version_information::version;
+
+    let response = version::blocking::call::<&str>(None).unwrap();
+    assert_eq!(response.api_version, "3.10");
+    assert!(!in_blocking_call());
+}
@@ -0,0 +1,224 @@
+use crate::client::constants::endpoint::Endpoint;
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+const SECOND: u64 = 1;
+const MINUTE: u64 = 60 * SECOND;
+const HOUR: u64 = 60 * MINUTE;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CachePolicy {
+    Disabled,
+    // Entries live for the given duration
+    Ttl(Duration),
+    // Entries live for the given duration or until a new `sched_num` is seen, whichever is first
+    Schedule(Duration),
+}
+
+impl CachePolicy {
+    pub fn default_for(endpoint: Endpoint) -> CachePolicy {
+        match endpoint {
+            // BART refreshes estimates roughly every 30 seconds
+            Endpoint::Etd => CachePolicy::Ttl(Duration::from_secs(30 * SECOND)),
+            Endpoint::Bsa | Endpoint::Elev | Endpoint::Count => {
+                CachePolicy::Ttl(Duration::from_secs(MINUTE))
+            }
+            Endpoint::Version => CachePolicy::Ttl(Duration::from_secs(HOUR)),
+            // Trip plans are relative to "now", so they can't live as long as the rest of the
+            // schedule
+            Endpoint::Arrive | Endpoint::Depart => {
+                CachePolicy::Schedule(Duration::from_secs(MINUTE))
+            }
+            Endpoint::Stns
+            | Endpoint::StnInfo
+            | Endpoint::StnAccess
+            | Endpoint::Routes
+            | Endpoint::RouteInfo => CachePolicy::Schedule(Duration::from_secs(24 * HOUR)),
+        }
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        match self {
+            CachePolicy::Disabled => None,
+            CachePolicy::Ttl(ttl) | CachePolicy::Schedule(ttl) => Some(*ttl),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    endpoint: Endpoint,
+    body: Arc<String>,
+    expires: Instant,
+}
+
+#[derive(Debug)]
+struct CacheInner {
+    entries: HashMap<String, CacheEntry>,
+    policies: HashMap<Endpoint, CachePolicy>,
+    sched_num: Option<i32>,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+pub struct Cache {
+    inner: Mutex<CacheInner>,
+}
+
+impl Cache {
+    pub fn new(capacity: usize) -> Cache {
+        Cache {
+            inner: Mutex::new(CacheInner {
+                entries: HashMap::new(),
+                policies: HashMap::new(),
+                sched_num: None,
+                capacity,
+            }),
+        }
+    }
+
+    pub fn policy(&self, endpoint: Endpoint) -> CachePolicy {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .policies
+            .get(&endpoint)
+            .cloned()
+            .unwrap_or_else(|| CachePolicy::default_for(endpoint))
+    }
+
+    pub fn set_policy(&self, endpoint: Endpoint, policy: CachePolicy) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.retain(|_, entry| entry.endpoint != endpoint);
+        inner.policies.insert(endpoint, policy);
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.capacity = capacity;
+        inner.evict(Instant::now());
+    }
+
+    pub fn sched_num(&self) -> Option<i32> {
+        self.inner.lock().unwrap().sched_num
+    }
+
+    pub fn get<T: AsRef<str>>(&self, key: T) -> Option<Arc<String>> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        match inner.entries.get(key.as_ref()) {
+            Some(entry) if entry.expires > now => Some(entry.body.clone()),
+            Some(_) => {
+                inner.entries.remove(key.as_ref());
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert<T: Into<String>>(&self, endpoint: Endpoint, key: T, body: Arc<String>) {
+        let ttl = match self.policy(endpoint).ttl() {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        inner.entries.insert(
+            key.into(),
+            CacheEntry {
+                endpoint,
+                body,
+                expires: now + ttl,
+            },
+        );
+        inner.evict(now);
+    }
+
+    // Drops everything cached under a `CachePolicy::Schedule` policy when BART publishes a new
+    // schedule. Until the first `sched_num` is seen there's no telling which schedule cached
+    // entries came from, so that counts as a new one too.
+    pub fn observe_sched_num(&self, sched_num: i32) {
+        let mut inner = self.inner.lock().unwrap();
+        let previous = inner.sched_num.replace(sched_num);
+        if previous == Some(sched_num) || inner.entries.is_empty() {
+            return;
+        }
+
+        let CacheInner {
+            entries, policies, ..
+        } = &mut *inner;
+        entries.retain(|_, entry| {
+            let policy = policies
+                .get(&entry.endpoint)
+                .cloned()
+                .unwrap_or_else(|| CachePolicy::default_for(entry.endpoint));
+            match policy {
+                CachePolicy::Schedule(_) => false,
+                _ => true,
+            }
+        });
+    }
+
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+}
+
+impl CacheInner {
+    fn evict(&mut self, now: Instant) {
+        if self.entries.len() <= self.capacity {
+            return;
+        }
+
+        self.entries.retain(|_, entry| entry.expires > now);
+
+        while self.entries.len() > self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Cache::new(DEFAULT_CAPACITY)
+    }
+}
+
+lazy_static! {
+    static ref CACHE: Cache = Cache::default();
+}
+
+pub fn global() -> &'static Cache {
+    &CACHE
+}
+
+#[test]
+fn cache_schedule_invalidation() {
+    let cache = Cache::default();
+    let body = Arc::new(String::from("{}"));
+    cache.insert(Endpoint::Stns, "stns", body.clone());
+    cache.insert(Endpoint::Bsa, "bsa", body.clone());
+
+    cache.observe_sched_num(1);
+    assert_eq!(cache.get("stns"), None);
+    cache.insert(Endpoint::Stns, "stns", body.clone());
+    cache.observe_sched_num(1);
+    assert_eq!(cache.get("stns"), Some(body.clone()));
+
+    cache.observe_sched_num(2);
+    assert_eq!(cache.get("stns"), None);
+    assert_eq!(cache.get("bsa"), Some(body));
+}
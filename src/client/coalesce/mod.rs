@@ -0,0 +1,135 @@
+use anyhow::Result;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+type SharedFetch = Shared<BoxFuture<'static, std::result::Result<Arc<String>, Arc<anyhow::Error>>>>;
+
+// The error of a call shared by several callers. It keeps the original error whole, so callers can
+// still downcast it through `inner`.
+#[derive(Debug, Clone)]
+pub struct SharedError(Arc<anyhow::Error>);
+
+impl SharedError {
+    pub fn inner(&self) -> &anyhow::Error {
+        &self.0
+    }
+}
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl Error for SharedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+// Deduplicates identical in-flight requests so that concurrent callers share one upstream call
+#[derive(Default)]
+pub struct SingleFlight {
+    // Each call is numbered, so that a caller finishing late can't clear a newer call's entry
+    in_flight: Mutex<HashMap<String, (usize, SharedFetch)>>,
+    next: AtomicUsize,
+}
+
+impl SingleFlight {
+    pub fn new() -> SingleFlight {
+        SingleFlight::default()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    pub async fn run<F>(&self, key: String, fetch: F) -> Result<Arc<String>>
+    where
+        F: Future<Output = Result<Arc<String>>> + Send + 'static,
+    {
+        let (id, shared) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    let id = self.next.fetch_add(1, Ordering::Relaxed);
+                    let shared = fetch
+                        .map(|result| result.map_err(Arc::new))
+                        .boxed()
+                        .shared();
+                    (id, shared)
+                })
+                .clone()
+        };
+
+        let result = shared.await;
+
+        // Whoever finishes first clears the entry so the next call goes upstream again
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.get(&key).map(|(current, _)| *current) == Some(id) {
+            in_flight.remove(&key);
+        }
+
+        result.map_err(|error| anyhow::Error::new(SharedError(error)))
+    }
+}
+
+lazy_static! {
+    static ref SINGLE_FLIGHT: SingleFlight = SingleFlight::new();
+}
+
+#[cfg(feature = "blocking")]
+lazy_static! {
+    static ref BLOCKING_SINGLE_FLIGHT: SingleFlight = SingleFlight::new();
+}
+
+// Calls in flight are polled by whoever shares them, so blocking calls only share theirs with
+// each other
+pub fn global() -> &'static SingleFlight {
+    #[cfg(feature = "blocking")]
+    {
+        if crate::client::blocking::in_blocking_call() {
+            return &BLOCKING_SINGLE_FLIGHT;
+        }
+    }
+    &SINGLE_FLIGHT
+}
+
+#[tokio::test]
+async fn coalesce_identical_calls() {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+    use tokio::timer::delay_for;
+
+    let single_flight = SingleFlight::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let fetch = || {
+        let calls = calls.clone();
+        async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            delay_for(Duration::from_millis(50)).await;
+            Ok(Arc::new(String::from("{}")))
+        }
+    };
+
+    let (first, second) = futures::join!(
+        single_flight.run(String::from("etd"), fetch()),
+        single_flight.run(String::from("etd"), fetch()),
+    );
+    assert_eq!(first.unwrap(), second.unwrap());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(single_flight.in_flight(), 0);
+}
@@ -8,7 +8,7 @@ pub const DIRECTION_CODE_SOUTHBOUND: &str = "s";
 pub const DIRECTION_FULL_NORTHBOUND: &str = "North";
 pub const DIRECTION_FULL_SOUTHBOUND: &str = "South";
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Direction {
     Northbound,
     Southbound,
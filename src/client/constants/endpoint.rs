@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+
+pub const ENDPOINT_CODE_ETD: &str = "etd";
+pub const ENDPOINT_CODE_BSA: &str = "bsa";
+pub const ENDPOINT_CODE_COUNT: &str = "count";
+pub const ENDPOINT_CODE_ELEV: &str = "elev";
+pub const ENDPOINT_CODE_STNS: &str = "stns";
+pub const ENDPOINT_CODE_STNINFO: &str = "stninfo";
+pub const ENDPOINT_CODE_STNACCESS: &str = "stnaccess";
+pub const ENDPOINT_CODE_ROUTES: &str = "routes";
+pub const ENDPOINT_CODE_ROUTEINFO: &str = "routeinfo";
+pub const ENDPOINT_CODE_ARRIVE: &str = "arrive";
+pub const ENDPOINT_CODE_DEPART: &str = "depart";
+pub const ENDPOINT_CODE_VERSION: &str = "version";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    Etd,
+    Bsa,
+    Count,
+    Elev,
+    Stns,
+    StnInfo,
+    StnAccess,
+    Routes,
+    RouteInfo,
+    Arrive,
+    Depart,
+    Version,
+}
+
+pub const ENDPOINTS: [Endpoint; 12] = [
+    Endpoint::Etd,
+    Endpoint::Bsa,
+    Endpoint::Count,
+    Endpoint::Elev,
+    Endpoint::Stns,
+    Endpoint::StnInfo,
+    Endpoint::StnAccess,
+    Endpoint::Routes,
+    Endpoint::RouteInfo,
+    Endpoint::Arrive,
+    Endpoint::Depart,
+    Endpoint::Version,
+];
+
+impl Endpoint {
+    pub fn from_code<T: AsRef<str>>(code: T) -> Result<Endpoint> {
+        match code.as_ref() {
+            ENDPOINT_CODE_ETD => Ok(Endpoint::Etd),
+            ENDPOINT_CODE_BSA => Ok(Endpoint::Bsa),
+            ENDPOINT_CODE_COUNT => Ok(Endpoint::Count),
+            ENDPOINT_CODE_ELEV => Ok(Endpoint::Elev),
+            ENDPOINT_CODE_STNS => Ok(Endpoint::Stns),
+            ENDPOINT_CODE_STNINFO => Ok(Endpoint::StnInfo),
+            ENDPOINT_CODE_STNACCESS => Ok(Endpoint::StnAccess),
+            ENDPOINT_CODE_ROUTES => Ok(Endpoint::Routes),
+            ENDPOINT_CODE_ROUTEINFO => Ok(Endpoint::RouteInfo),
+            ENDPOINT_CODE_ARRIVE => Ok(Endpoint::Arrive),
+            ENDPOINT_CODE_DEPART => Ok(Endpoint::Depart),
+            ENDPOINT_CODE_VERSION => Ok(Endpoint::Version),
+            _ => Err(anyhow!("Does not match any endpoint")),
+        }
+    }
+
+    pub fn to_code(&self) -> &'static str {
+        match self {
+            Endpoint::Etd => ENDPOINT_CODE_ETD,
+            Endpoint::Bsa => ENDPOINT_CODE_BSA,
+            Endpoint::Count => ENDPOINT_CODE_COUNT,
+            Endpoint::Elev => ENDPOINT_CODE_ELEV,
+            Endpoint::Stns => ENDPOINT_CODE_STNS,
+            Endpoint::StnInfo => ENDPOINT_CODE_STNINFO,
+            Endpoint::StnAccess => ENDPOINT_CODE_STNACCESS,
+            Endpoint::Routes => ENDPOINT_CODE_ROUTES,
+            Endpoint::RouteInfo => ENDPOINT_CODE_ROUTEINFO,
+            Endpoint::Arrive => ENDPOINT_CODE_ARRIVE,
+            Endpoint::Depart => ENDPOINT_CODE_DEPART,
+            Endpoint::Version => ENDPOINT_CODE_VERSION,
+        }
+    }
+}
+
+impl TryFrom<String> for Endpoint {
+    type Error = anyhow::Error;
+
+    fn try_from(endpoint_string: String) -> std::result::Result<Self, Self::Error> {
+        Endpoint::from_code(&endpoint_string)
+    }
+}
+
+impl Serialize for Endpoint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Endpoint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Endpoint::try_from(s).map_err(serde::de::Error::custom)
+    }
+}
@@ -1,6 +1,7 @@
 pub mod color;
 pub mod datetime;
 pub mod direction;
+pub mod endpoint;
 pub mod fare_type;
 pub mod station;
 
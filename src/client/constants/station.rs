@@ -100,7 +100,7 @@ pub const STATION_FULL_WALNUT_CREEK: &str = "Walnut Creek";
 pub const STATION_FULL_WEST_DUBLIN: &str = "West Dublin";
 pub const STATION_FULL_WEST_OAKLAND: &str = "West Oakland";
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Station {
     OaklandCityCenter12thSt,
     SFMission16thSt,
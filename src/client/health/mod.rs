@@ -0,0 +1,93 @@
+use crate::client::constants::endpoint::Endpoint;
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointHealth {
+    pub last_success: Option<Instant>,
+    pub last_failure: Option<Instant>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+impl EndpointHealth {
+    // How old the freshest data fetched from BART is
+    pub fn age(&self) -> Option<Duration> {
+        self.last_success.map(|last_success| last_success.elapsed())
+    }
+
+    // Whether the last call went through and did so within `max_age`
+    pub fn is_fresh(&self, max_age: Duration) -> bool {
+        self.consecutive_failures == 0 && self.age().map_or(false, |age| age <= max_age)
+    }
+}
+
+// Outcomes of the calls that actually reached BART, cache hits don't count
+#[derive(Debug, Default)]
+pub struct Health {
+    endpoints: Mutex<HashMap<Endpoint, EndpointHealth>>,
+}
+
+impl Health {
+    pub fn new() -> Health {
+        Health::default()
+    }
+
+    pub fn record_success(&self, endpoint: Endpoint) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let health = endpoints.entry(endpoint).or_default();
+        health.last_success = Some(Instant::now());
+        health.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&self, endpoint: Endpoint, error: &anyhow::Error) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let health = endpoints.entry(endpoint).or_default();
+        health.last_failure = Some(Instant::now());
+        health.last_error = Some(format!("{:#}", error));
+        health.consecutive_failures += 1;
+    }
+
+    pub fn get(&self, endpoint: Endpoint) -> EndpointHealth {
+        self.endpoints
+            .lock()
+            .unwrap()
+            .get(&endpoint)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn snapshot(&self) -> HashMap<Endpoint, EndpointHealth> {
+        self.endpoints.lock().unwrap().clone()
+    }
+}
+
+lazy_static! {
+    static ref HEALTH: Health = Health::new();
+}
+
+pub fn global() -> &'static Health {
+    &HEALTH
+}
+
+#[test]
+fn failures_make_stale() {
+    let health = Health::new();
+    assert!(!health
+        .get(Endpoint::Version)
+        .is_fresh(Duration::from_secs(60)));
+
+    health.record_success(Endpoint::Version);
+    assert!(health
+        .get(Endpoint::Version)
+        .is_fresh(Duration::from_secs(60)));
+
+    health.record_failure(Endpoint::Version, &anyhow::anyhow!("Invalid key"));
+    let version = health.get(Endpoint::Version);
+    assert!(!version.is_fresh(Duration::from_secs(60)));
+    assert_eq!(version.last_error, Some(String::from("Invalid key")));
+}
@@ -0,0 +1,274 @@
+use crate::client::{constants::PUBLIC_KEY, request::bart_error};
+use lazy_static::lazy_static;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use url::Url;
+
+// How long a key sits out after BART rejects it, or asks us to slow down
+pub const INVALID_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+pub const THROTTLED_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outage {
+    Invalid,
+    Throttled,
+}
+
+impl Outage {
+    fn cooldown(self) -> Duration {
+        match self {
+            Outage::Invalid => INVALID_COOLDOWN,
+            Outage::Throttled => THROTTLED_COOLDOWN,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Outage::Invalid => "rejected",
+            Outage::Throttled => "throttled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyHealth {
+    pub requests: u64,
+    pub rejections: u64,
+    pub throttles: u64,
+    pub out_until: Option<Instant>,
+    pub last_error: Option<String>,
+}
+
+impl KeyHealth {
+    pub fn in_rotation(&self, now: Instant) -> bool {
+        self.out_until.map_or(true, |until| until <= now)
+    }
+}
+
+// The key a call should be made with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    // The pool is empty or the call brought its own key, so it keeps it
+    Unmanaged,
+    Key(String),
+    // Every key in the pool is sitting out
+    Exhausted,
+}
+
+// BART answers a bad key with a 200 and an error message in place of the usual response
+pub fn rejection(body: &str) -> Option<Outage> {
+    let text = bart_error(body)?.to_lowercase();
+    if text.contains("limit") || text.contains("exceeded") {
+        Some(Outage::Throttled)
+    } else if text.contains("key") {
+        Some(Outage::Invalid)
+    } else {
+        None
+    }
+}
+
+fn key_of(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()?
+        .query_pairs()
+        .find(|(name, _)| name == "key")
+        .map(|(_, value)| value.into_owned())
+}
+
+// `url` with its `key` swapped for `key`, keeping every other parameter where it was
+pub fn with_key(url: &str, key: &str) -> String {
+    let mut parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return String::from(url),
+    };
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if name == "key" {
+                String::from(key)
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.into_string()
+}
+
+// Enough of a key to tell keys apart in logs and on `/status`: at most its last four characters,
+// and never more than a quarter of it, so short keys are hidden entirely
+pub fn mask(key: &str) -> String {
+    let shown = (key.chars().count() / 4).min(4);
+    let start = key
+        .char_indices()
+        .rev()
+        .take(shown)
+        .last()
+        .map_or(key.len(), |(index, _)| index);
+    format!("…{}", &key[start..])
+}
+
+// Spreads calls round-robin over the keys in rotation. Calls made with the public key or one of
+// the pool's keys are given whichever key is next; calls made with any other key keep it.
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: Mutex<Vec<(String, KeyHealth)>>,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    pub fn new(keys: Vec<String>) -> KeyPool {
+        let pool = KeyPool {
+            keys: Mutex::new(vec![]),
+            next: AtomicUsize::new(0),
+        };
+        pool.set_keys(keys);
+        pool
+    }
+
+    // An empty pool leaves every call with the key it was made with
+    pub fn set_keys(&self, keys: Vec<String>) {
+        let mut pool = self.keys.lock().unwrap();
+        *pool = keys
+            .into_iter()
+            .map(|key| {
+                let health = pool
+                    .iter()
+                    .find(|(existing, _)| existing == &key)
+                    .map(|(_, health)| health.clone())
+                    .unwrap_or_default();
+                (key, health)
+            })
+            .collect();
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn select(&self, url: &str) -> Selection {
+        self.select_at(url, Instant::now())
+    }
+
+    fn select_at(&self, url: &str, now: Instant) -> Selection {
+        let mut keys = self.keys.lock().unwrap();
+        if keys.is_empty() {
+            return Selection::Unmanaged;
+        }
+        let current = match key_of(url) {
+            Some(current) => current,
+            None => return Selection::Unmanaged,
+        };
+        if current != PUBLIC_KEY && !keys.iter().any(|(key, _)| key == &current) {
+            return Selection::Unmanaged;
+        }
+
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let len = keys.len();
+        let index = match (0..len)
+            .map(|offset| (start + offset) % len)
+            .find(|&index| keys[index].1.in_rotation(now))
+        {
+            Some(index) => index,
+            None => return Selection::Exhausted,
+        };
+        let (key, health) = &mut keys[index];
+        health.requests += 1;
+        Selection::Key(key.clone())
+    }
+
+    pub fn take_out(&self, key: &str, outage: Outage) {
+        self.take_out_at(key, outage, Instant::now())
+    }
+
+    fn take_out_at(&self, key: &str, outage: Outage, now: Instant) {
+        let mut keys = self.keys.lock().unwrap();
+        if let Some((_, health)) = keys.iter_mut().find(|(existing, _)| existing == key) {
+            match outage {
+                Outage::Invalid => health.rejections += 1,
+                Outage::Throttled => health.throttles += 1,
+            }
+            health.out_until = Some(now + outage.cooldown());
+            health.last_error = Some(format!("BART {} the key", outage.describe()));
+            log::warn!(
+                "BART {} key {}, taking it out of rotation for {}s",
+                outage.describe(),
+                mask(key),
+                outage.cooldown().as_secs()
+            );
+        }
+    }
+
+    // Keys in the order they were configured
+    pub fn snapshot(&self) -> Vec<(String, KeyHealth)> {
+        self.keys.lock().unwrap().clone()
+    }
+}
+
+impl Default for KeyPool {
+    fn default() -> Self {
+        KeyPool::new(vec![])
+    }
+}
+
+lazy_static! {
+    static ref KEY_POOL: KeyPool = KeyPool::default();
+}
+
+pub fn global() -> &'static KeyPool {
+    &KEY_POOL
+}
+
+#[test]
+fn keys_rotate_and_sit_out() {
+    let pool = KeyPool::new(vec![String::from("AAAA-1111"), String::from("BBBB-2222")]);
+    let url = format!(
+        "https://api.bart.gov/api/stn.aspx?cmd=stns&key={}",
+        PUBLIC_KEY
+    );
+    let now = Instant::now();
+
+    let key = |key: &str| Selection::Key(String::from(key));
+
+    let first = pool.select_at(&url, now);
+    let second = pool.select_at(&url, now);
+    assert_ne!(first, second);
+    assert_eq!(
+        pool.select_at("https://api.bart.gov/api/stn.aspx?cmd=stns&key=OTHER", now),
+        Selection::Unmanaged
+    );
+
+    pool.take_out_at("AAAA-1111", Outage::Throttled, now);
+    assert_eq!(pool.select_at(&url, now), key("BBBB-2222"));
+    assert_eq!(pool.select_at(&url, now), key("BBBB-2222"));
+    pool.take_out_at("BBBB-2222", Outage::Invalid, now);
+    assert_eq!(pool.select_at(&url, now), Selection::Exhausted);
+    assert_eq!(
+        pool.select_at(&url, now + THROTTLED_COOLDOWN),
+        key("AAAA-1111")
+    );
+
+    assert_eq!(
+        with_key(&url, "BBBB-2222"),
+        "https://api.bart.gov/api/stn.aspx?cmd=stns&key=BBBB-2222"
+    );
+    assert_eq!(
+        rejection(r#"{"root":{"message":{"error":{"text":"Invalid key"}}}}"#),
+        Some(Outage::Invalid)
+    );
+    assert_eq!(rejection(r#"{"root":{"message":""}}"#), None);
+
+    assert_eq!(mask("MW9S-E7SL-26DU-VV8V"), "…VV8V");
+    assert_eq!(mask("AAAA-111"), "…11");
+    assert_eq!(mask("abc"), "…");
+}
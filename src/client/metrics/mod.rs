@@ -0,0 +1,66 @@
+use crate::client::constants::endpoint::Endpoint;
+use lazy_static::lazy_static;
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+use std::time::Duration;
+
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_RETRY: &str = "retry";
+pub const OUTCOME_FAILURE: &str = "failure";
+
+pub const CACHE_HIT: &str = "hit";
+pub const CACHE_MISS: &str = "miss";
+
+// Registered with the default registry, so anything that gathers it sees the client's metrics
+lazy_static! {
+    static ref UPSTREAM_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "sfbart_upstream_requests_total",
+        "Requests made to BART, by endpoint and outcome",
+        &["endpoint", "outcome"]
+    )
+    .unwrap();
+    static ref UPSTREAM_DURATION: HistogramVec = register_histogram_vec!(
+        "sfbart_upstream_request_duration_seconds",
+        "Time taken by requests made to BART, by endpoint",
+        &["endpoint"]
+    )
+    .unwrap();
+    static ref CACHE_LOOKUPS: IntCounterVec = register_int_counter_vec!(
+        "sfbart_cache_lookups_total",
+        "Cache lookups, by endpoint and whether they hit",
+        &["endpoint", "result"]
+    )
+    .unwrap();
+}
+
+pub fn observe_request(endpoint: Endpoint, duration: Duration, outcome: &str) {
+    let seconds = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+    UPSTREAM_DURATION
+        .with_label_values(&[endpoint.to_code()])
+        .observe(seconds);
+    UPSTREAM_REQUESTS
+        .with_label_values(&[endpoint.to_code(), outcome])
+        .inc();
+}
+
+pub fn observe_cache(endpoint: Endpoint, hit: bool) {
+    let result = if hit { CACHE_HIT } else { CACHE_MISS };
+    CACHE_LOOKUPS
+        .with_label_values(&[endpoint.to_code(), result])
+        .inc();
+}
+
+pub fn upstream_requests(endpoint: Endpoint, outcome: &str) -> u64 {
+    UPSTREAM_REQUESTS
+        .with_label_values(&[endpoint.to_code(), outcome])
+        .get()
+}
+
+#[test]
+fn observe_request_counts() {
+    let before = upstream_requests(Endpoint::Version, OUTCOME_FAILURE);
+    observe_request(Endpoint::Version, Duration::from_millis(5), OUTCOME_FAILURE);
+    assert_eq!(
+        upstream_requests(Endpoint::Version, OUTCOME_FAILURE),
+        before + 1
+    );
+}
@@ -1,4 +1,15 @@
 pub mod apis;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
+pub mod coalesce;
 pub mod constants;
+pub mod health;
+pub mod keys;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod rate_limit;
+pub mod request;
+pub mod retry;
 
 mod serde_helpers;
@@ -0,0 +1,100 @@
+use lazy_static::lazy_static;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::timer::delay_for;
+
+// BART doesn't publish its limits, so stay well clear of them by default
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 5.0;
+pub const DEFAULT_BURST: f64 = 10.0;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    requests_per_second: f64,
+    burst: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.requests_per_second).min(self.burst);
+        self.refilled = now;
+    }
+
+    // Takes a token if one is available, otherwise says how long until one will be
+    fn take(&mut self) -> Option<Duration> {
+        self.refill(Instant::now());
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+        let missing = 1.0 - self.tokens;
+        Some(Duration::from_secs_f64(missing / self.requests_per_second))
+    }
+}
+
+// A token bucket shared by every endpoint call
+#[derive(Debug)]
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64, burst: f64) -> RateLimiter {
+        RateLimiter {
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                requests_per_second,
+                burst,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn set_rate(&self, requests_per_second: f64, burst: f64) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill(Instant::now());
+        bucket.requests_per_second = requests_per_second;
+        bucket.burst = burst;
+        bucket.tokens = bucket.tokens.min(burst);
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        self.bucket.lock().unwrap().take().is_none()
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = self.bucket.lock().unwrap().take();
+            match wait {
+                Some(wait) => delay_for(wait).await,
+                None => return,
+            }
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new(DEFAULT_REQUESTS_PER_SECOND, DEFAULT_BURST)
+    }
+}
+
+lazy_static! {
+    static ref RATE_LIMITER: RateLimiter = RateLimiter::default();
+}
+
+pub fn global() -> &'static RateLimiter {
+    &RATE_LIMITER
+}
+
+#[test]
+fn rate_limit_burst() {
+    let rate_limiter = RateLimiter::new(1.0, 2.0);
+    assert!(rate_limiter.try_acquire());
+    assert!(rate_limiter.try_acquire());
+    assert!(!rate_limiter.try_acquire());
+}
@@ -0,0 +1,263 @@
+use crate::client::{
+    cache, coalesce, constants::endpoint::Endpoint, health, keys, rate_limit, retry,
+};
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{error::Error, fmt, str::FromStr, sync::Arc, time::Duration};
+use tokio::{future::FutureExt, timer::delay_for};
+use url::Url;
+
+lazy_static! {
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::new();
+}
+
+#[cfg(feature = "blocking")]
+lazy_static! {
+    static ref BLOCKING_HTTP_CLIENT: reqwest::Client = reqwest::Client::new();
+}
+
+// Pooled connections belong to the runtime that opened them
+fn http_client() -> &'static reqwest::Client {
+    #[cfg(feature = "blocking")]
+    {
+        if crate::client::blocking::in_blocking_call() {
+            return &BLOCKING_HTTP_CLIENT;
+        }
+    }
+    &HTTP_CLIENT
+}
+
+#[derive(Deserialize)]
+struct SchedNum {
+    #[serde(default)]
+    sched_num: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Root {
+    root: SchedNum,
+}
+
+// The API key is left out so that responses are shared no matter which key fetched them, and the
+// query is sorted so that the same request always maps to the same entry
+pub fn cache_key<T: AsRef<str>>(url: T) -> String {
+    let mut parsed = match Url::parse(url.as_ref()) {
+        Ok(parsed) => parsed,
+        Err(_) => return String::from(url.as_ref()),
+    };
+    let mut pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| name != "key")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.into_string()
+}
+
+fn observe_sched_num(body: &str) {
+    let sched_num = serde_json::from_str::<Root>(body)
+        .ok()
+        .and_then(|root| root.root.sched_num)
+        .and_then(|sched_num| i32::from_str(&sched_num).ok());
+    if let Some(sched_num) = sched_num {
+        cache::global().observe_sched_num(sched_num);
+    }
+}
+
+enum Attempt {
+    Done(Arc<String>),
+    Retry(anyhow::Error),
+    // Retried like any other, but also says the key is being throttled
+    Throttled(anyhow::Error),
+    Fail(anyhow::Error),
+}
+
+async fn attempt(url: &str, timeout: Duration) -> Attempt {
+    let request = async {
+        let response = http_client().get(url).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        Ok::<_, reqwest::Error>((status, body))
+    };
+    match request.timeout(timeout).await {
+        Err(_) => Attempt::Retry(anyhow!("Request timed out after {:?}", timeout)),
+        Ok(Err(error)) => Attempt::Retry(error.into()),
+        Ok(Ok((status, _))) if status == StatusCode::TOO_MANY_REQUESTS => {
+            Attempt::Throttled(anyhow!("BART responded with {}", status))
+        }
+        Ok(Ok((status, _))) if status.is_server_error() => {
+            Attempt::Retry(anyhow!("BART responded with {}", status))
+        }
+        Ok(Ok((status, _))) if !status.is_success() => {
+            Attempt::Fail(anyhow!("BART responded with {}", status))
+        }
+        Ok(Ok((_, body))) => Attempt::Done(Arc::new(body)),
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn observe_attempt(endpoint: Endpoint, started: std::time::Instant, attempt: &Attempt) {
+    use crate::client::metrics;
+    let outcome = match attempt {
+        Attempt::Done(_) => metrics::OUTCOME_SUCCESS,
+        Attempt::Retry(_) | Attempt::Throttled(_) => metrics::OUTCOME_RETRY,
+        Attempt::Fail(_) => metrics::OUTCOME_FAILURE,
+    };
+    metrics::observe_request(endpoint, started.elapsed(), outcome);
+}
+
+// Every call is a GET, so all of them are safe to retry. A key BART rejects or throttles is taken
+// out of the pool, and the call moves straight on to the next key without counting as a retry.
+// Once every key is out, calls fail rather than fall back to a key that was never meant for them.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+async fn fetch(endpoint: Endpoint, url: String) -> Result<Arc<String>> {
+    let policy = retry::policy();
+    let pool = keys::global();
+    let mut retries = 0;
+    let mut failovers = 0;
+    loop {
+        rate_limit::global().acquire().await;
+        let key = match pool.select(&url) {
+            keys::Selection::Key(key) => Some(key),
+            keys::Selection::Unmanaged => None,
+            keys::Selection::Exhausted => {
+                return Err(anyhow!("Every BART API key is out of rotation"));
+            }
+        };
+        let keyed = match &key {
+            Some(key) => keys::with_key(&url, key),
+            None => url.clone(),
+        };
+        #[cfg(feature = "metrics")]
+        let started = std::time::Instant::now();
+        let result = attempt(&keyed, policy.timeout).await;
+        #[cfg(feature = "metrics")]
+        observe_attempt(endpoint, started, &result);
+
+        let outage = match (&key, &result) {
+            (Some(_), Attempt::Done(body)) => keys::rejection(body),
+            (Some(_), Attempt::Throttled(_)) => Some(keys::Outage::Throttled),
+            _ => None,
+        };
+        if let (Some(key), Some(outage)) = (&key, outage) {
+            pool.take_out(key, outage);
+            if failovers < pool.len() {
+                failovers += 1;
+                continue;
+            }
+        }
+
+        match result {
+            Attempt::Done(body) => return Ok(body),
+            Attempt::Fail(error) => return Err(error),
+            Attempt::Retry(error) | Attempt::Throttled(error) => {
+                if retries >= policy.max_retries {
+                    return Err(error);
+                }
+                delay_for(policy.backoff(retries)).await;
+                retries += 1;
+            }
+        }
+    }
+}
+
+// An error BART reported in place of the response, along with the body it came in
+#[derive(Debug, Clone)]
+pub struct BartError {
+    pub message: String,
+    pub body: Arc<String>,
+}
+
+impl fmt::Display for BartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BART responded with an error: {}", self.message)
+    }
+}
+
+impl Error for BartError {}
+
+// BART reports problems such as a rejected key inside an otherwise well formed response
+pub fn bart_error(body: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()?
+        .pointer("/root/message/error/text")
+        .and_then(serde_json::Value::as_str)
+        .map(String::from)
+}
+
+// Only what `parse` accepts is cached or counts as a success, so an error from BART, which is
+// shared by every key since keys aren't part of the cache key, is never served twice
+async fn load<R, T, F>(endpoint: Endpoint, url: T, parse: F) -> Result<R>
+where
+    T: AsRef<str>,
+    F: FnOnce(&Arc<String>) -> Result<R>,
+{
+    let cache = cache::global();
+    let key = cache_key(&url);
+
+    let cached = cache.get(&key);
+    #[cfg(feature = "metrics")]
+    crate::client::metrics::observe_cache(endpoint, cached.is_some());
+    if let Some(body) = cached {
+        return parse(&body);
+    }
+
+    let parsed = coalesce::global()
+        .run(key.clone(), fetch(endpoint, String::from(url.as_ref())))
+        .await
+        .and_then(|body| {
+            if let Some(message) = bart_error(&body) {
+                return Err(BartError { message, body }.into());
+            }
+            let parsed = parse(&body)?;
+            Ok((body, parsed))
+        });
+    let (body, parsed) = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            health::global().record_failure(endpoint, &error);
+            return Err(error);
+        }
+    };
+    health::global().record_success(endpoint);
+    observe_sched_num(&body);
+    cache.insert(endpoint, key, body);
+    Ok(parsed)
+}
+
+// The response body exactly as BART sent it, as long as it is JSON
+pub async fn get_body<T: AsRef<str>>(endpoint: Endpoint, url: T) -> Result<Arc<String>> {
+    load(endpoint, url, |body| {
+        serde_json::from_str::<serde_json::Value>(body)?;
+        Ok(body.clone())
+    })
+    .await
+}
+
+pub async fn get_json<R, T>(endpoint: Endpoint, url: T) -> Result<R>
+where
+    R: DeserializeOwned,
+    T: AsRef<str>,
+{
+    load(endpoint, url, |body| Ok(serde_json::from_str(body)?)).await
+}
+
+#[test]
+fn cache_key_without_key() {
+    assert_eq!(
+        cache_key("https://api.bart.gov/api/stn.aspx?json=y&key=MW9S-E7SL-26DU-VV8V&cmd=stns"),
+        "https://api.bart.gov/api/stn.aspx?cmd=stns&json=y"
+    );
+}
+
+#[test]
+fn bart_errors_in_bodies() {
+    assert_eq!(
+        bart_error(r#"{"root":{"message":{"error":{"text":"Invalid key"}}}}"#),
+        Some(String::from("Invalid key"))
+    );
+    assert_eq!(bart_error(r#"{"root":{"message":""}}"#), None);
+}
@@ -0,0 +1,74 @@
+use lazy_static::lazy_static;
+use rand::Rng;
+use std::{cmp, sync::RwLock, time::Duration};
+
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(250);
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(10);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    // Retries after the first attempt, so a value of `0` disables retrying
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub timeout: Duration,
+}
+
+impl RetryPolicy {
+    pub fn disabled() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // Exponential backoff with "full jitter": a random delay between zero and
+    // `base_delay * 2^retry`, capped at `max_delay`
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let base = self.base_delay.as_millis() as u64;
+        let exponential = base.saturating_mul(2u64.saturating_pow(retry));
+        let ceiling = cmp::min(exponential, self.max_delay.as_millis() as u64);
+        if ceiling == 0 {
+            return Duration::from_millis(0);
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(0, ceiling + 1))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+lazy_static! {
+    static ref POLICY: RwLock<RetryPolicy> = RwLock::new(RetryPolicy::default());
+}
+
+pub fn policy() -> RetryPolicy {
+    POLICY.read().unwrap().clone()
+}
+
+pub fn set_policy(policy: RetryPolicy) {
+    *POLICY.write().unwrap() = policy;
+}
+
+#[test]
+fn backoff_is_capped() {
+    let policy = RetryPolicy {
+        max_retries: 10,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        timeout: DEFAULT_TIMEOUT,
+    };
+    for retry in 0..10 {
+        assert!(policy.backoff(retry) <= Duration::from_secs(1));
+    }
+}
@@ -1 +1,4 @@
+#[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "server")]
+pub mod server;
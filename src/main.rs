@@ -1,23 +1,11 @@
-use futures::{FutureExt, StreamExt};
-use warp::Filter;
+use sfbart::server::{self, config::Config};
 
 #[tokio::main]
-async fn main() {
-    let routes = warp::path("echo")
-        // The `ws2()` filter will prepare the Websocket handshake.
-        .and(warp::ws2())
-        .map(|ws: warp::ws::Ws2| {
-            // And then our closure will be called when it completes...
-            ws.on_upgrade(|websocket| {
-                // Just echo all messages back...
-                let (tx, rx) = websocket.split();
-                rx.forward(tx).map(|result| {
-                    if let Err(e) = result {
-                        eprintln!("websocket error: {:?}", e);
-                    }
-                })
-            })
-        });
-
-    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+async fn main() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    env_logger::Builder::new()
+        .filter_level(config.log_level)
+        .init();
+    server::serve(config).await;
+    Ok(())
 }
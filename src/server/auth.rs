@@ -0,0 +1,345 @@
+use crate::server::{
+    config::{Api, ClientConfig},
+    rest::ErrorResponse,
+    Routes,
+};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use warp::{filters::BoxedFilter, http::StatusCode, Filter, Reply};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+// Where websocket clients, which can't always set headers, put their key in `connection_init`
+pub const API_KEY_PAYLOAD_FIELD: &str = "apiKey";
+
+pub const OUTCOME_ALLOWED: &str = "allowed";
+pub const OUTCOME_RATE_LIMITED: &str = "rate_limited";
+pub const OUTCOME_QUOTA_EXCEEDED: &str = "quota_exceeded";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    MissingKey,
+    UnknownKey,
+    RateLimited,
+    QuotaExceeded,
+}
+
+impl Denied {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Denied::MissingKey | Denied::UnknownKey => StatusCode::UNAUTHORIZED,
+            Denied::RateLimited | Denied::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Denied::MissingKey => "An API key is required",
+            Denied::UnknownKey => "Unknown API key",
+            Denied::RateLimited => "Rate limit exceeded",
+            Denied::QuotaExceeded => "Daily quota exceeded",
+        }
+    }
+
+    pub fn reply(&self) -> Box<dyn Reply> {
+        let body = ErrorResponse {
+            error: String::from(self.message()),
+        };
+        Box::new(warp::reply::with_status(
+            warp::reply::json(&body),
+            self.status(),
+        ))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Usage {
+    // Admitted requests since the server started, by API
+    pub requests: BTreeMap<String, u64>,
+    pub rate_limited: u64,
+    pub quota_exceeded: u64,
+    pub today: u64,
+    pub daily_quota: Option<u64>,
+}
+
+// Refills continuously, so a client can spend a minute's worth of requests at once but no more
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct State {
+    bucket: Bucket,
+    day: NaiveDate,
+    usage: Usage,
+}
+
+pub struct Client {
+    config: ClientConfig,
+    state: Mutex<State>,
+}
+
+impl Client {
+    fn new(config: ClientConfig) -> Client {
+        let tokens = f64::from(config.requests_per_minute.unwrap_or(0));
+        let usage = Usage {
+            daily_quota: config.daily_quota,
+            ..Usage::default()
+        };
+        Client {
+            config,
+            state: Mutex::new(State {
+                bucket: Bucket {
+                    tokens,
+                    updated: Instant::now(),
+                },
+                day: Utc::today().naive_utc(),
+                usage,
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    // Counts one request to `api` against the client's rate limit and quota
+    pub fn admit(&self, api: Api) -> Result<(), Denied> {
+        self.check(api)?;
+        self.charge(api);
+        Ok(())
+    }
+
+    // Whether the client has a request to spare, without spending it
+    pub fn check(&self, api: Api) -> Result<(), Denied> {
+        let checked = self.check_at(Instant::now(), Utc::today().naive_utc());
+        match checked {
+            Ok(()) => {}
+            Err(Denied::QuotaExceeded) => self.observe(api, OUTCOME_QUOTA_EXCEEDED),
+            Err(_) => self.observe(api, OUTCOME_RATE_LIMITED),
+        }
+        checked
+    }
+
+    // Spends one request to `api`, once it has been served
+    pub fn charge(&self, api: Api) {
+        self.charge_at(api, Instant::now(), Utc::today().naive_utc());
+        self.observe(api, OUTCOME_ALLOWED);
+    }
+
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn observe(&self, api: Api, outcome: &'static str) {
+        #[cfg(feature = "metrics")]
+        crate::server::metrics::observe_client_request(self.name(), api.to_code(), outcome);
+    }
+
+    #[cfg(test)]
+    fn admit_at(&self, api: Api, now: Instant, today: NaiveDate) -> Result<(), Denied> {
+        self.check_at(now, today)?;
+        self.charge_at(api, now, today);
+        Ok(())
+    }
+
+    fn check_at(&self, now: Instant, today: NaiveDate) -> Result<(), Denied> {
+        let mut state = self.state.lock().unwrap();
+        refill(&mut state, &self.config, now, today);
+        if let Some(quota) = self.config.daily_quota {
+            if state.usage.today >= quota {
+                state.usage.quota_exceeded += 1;
+                return Err(Denied::QuotaExceeded);
+            }
+        }
+
+        if self.config.requests_per_minute.is_some() && state.bucket.tokens < 1.0 {
+            state.usage.rate_limited += 1;
+            return Err(Denied::RateLimited);
+        }
+        Ok(())
+    }
+
+    // Requests that were checked together may all be charged, so the bucket can dip below zero
+    // and the client waits for it to refill
+    fn charge_at(&self, api: Api, now: Instant, today: NaiveDate) {
+        let mut state = self.state.lock().unwrap();
+        refill(&mut state, &self.config, now, today);
+        if self.config.requests_per_minute.is_some() {
+            state.bucket.tokens -= 1.0;
+        }
+        state.usage.today += 1;
+        *state
+            .usage
+            .requests
+            .entry(String::from(api.to_code()))
+            .or_insert(0) += 1;
+    }
+
+    pub fn usage(&self) -> Usage {
+        self.state.lock().unwrap().usage.clone()
+    }
+}
+
+// Rolls the day over and tops the bucket up for the time since it was last touched
+fn refill(state: &mut State, config: &ClientConfig, now: Instant, today: NaiveDate) {
+    if state.day != today {
+        state.day = today;
+        state.usage.today = 0;
+    }
+    if let Some(per_minute) = config.requests_per_minute {
+        let per_minute = f64::from(per_minute);
+        let elapsed = now.duration_since(state.bucket.updated);
+        let refilled = (elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1e3)
+            * per_minute
+            / 60.0;
+        state.bucket.tokens = (state.bucket.tokens + refilled).min(per_minute);
+        state.bucket.updated = now;
+    }
+}
+
+// Compares every byte whatever the first difference, so the time taken doesn't give away how much
+// of a key was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter()
+        .zip(b)
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+// Every configured client
+pub struct Clients {
+    clients: Vec<Arc<Client>>,
+}
+
+impl Clients {
+    pub fn new(configs: &[ClientConfig]) -> Clients {
+        Clients {
+            clients: configs
+                .iter()
+                .map(|config| Arc::new(Client::new(config.clone())))
+                .collect(),
+        }
+    }
+
+    // Without any clients the server is open, and nobody is authenticated
+    pub fn is_open(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    pub fn authenticate(&self, key: Option<&str>) -> Result<Option<Arc<Client>>, Denied> {
+        if self.is_open() {
+            return Ok(None);
+        }
+        let key = key.ok_or(Denied::MissingKey)?;
+        // Every key is compared, rather than stopping at the match
+        let mut found = None;
+        for client in &self.clients {
+            if constant_time_eq(client.config.key.as_bytes(), key.as_bytes()) {
+                found = Some(client.clone());
+            }
+        }
+        found.map(Some).ok_or(Denied::UnknownKey)
+    }
+
+    // Authenticates and checks the client has a request to spare, without charging it yet
+    pub fn authorize(&self, key: Option<&str>, api: Api) -> Result<Option<Arc<Client>>, Denied> {
+        let client = self.authenticate(key)?;
+        if let Some(client) = &client {
+            client.check(api)?;
+        }
+        Ok(client)
+    }
+}
+
+// Answers requests matching `scope` that aren't allowed through, and leaves the rest to `routes`.
+// Clients are only charged for requests one of the routes went on to answer.
+pub fn protect(clients: Arc<Clients>, api: Api, scope: BoxedFilter<()>, routes: Routes) -> Routes {
+    if clients.is_open() {
+        return routes;
+    }
+    let authorize = clients.clone();
+    let denied = scope
+        .and(warp::header::optional::<String>(API_KEY_HEADER))
+        .and_then(move |key: Option<String>| {
+            let authorized = authorize.authorize(key.as_ref().map(String::as_str), api);
+            async move {
+                match authorized {
+                    Ok(_) => Err(warp::reject::not_found()),
+                    Err(denied) => Ok::<_, warp::Rejection>(denied.reply()),
+                }
+            }
+        });
+    let charged = warp::header::optional::<String>(API_KEY_HEADER)
+        .and(routes)
+        .map(move |key: Option<String>, reply: Box<dyn Reply>| {
+            if let Ok(Some(client)) = clients.authenticate(key.as_ref().map(String::as_str)) {
+                client.charge(api);
+            }
+            reply
+        });
+    denied.or(charged).unify().boxed()
+}
+
+// Lets each client see its own usage
+pub fn routes(clients: Arc<Clients>) -> Routes {
+    warp::path("usage")
+        .and(warp::path::end())
+        .and(warp::get2())
+        .and(warp::header::optional::<String>(API_KEY_HEADER))
+        .map(move |key: Option<String>| {
+            match clients.authenticate(key.as_ref().map(String::as_str)) {
+                Ok(Some(client)) => Box::new(warp::reply::json(&client.usage())) as Box<dyn Reply>,
+                Ok(None) => Box::new(warp::reply::with_status(
+                    warp::reply::json(&ErrorResponse {
+                        error: String::from("No clients are configured"),
+                    }),
+                    StatusCode::NOT_FOUND,
+                )) as Box<dyn Reply>,
+                Err(denied) => denied.reply(),
+            }
+        })
+        .boxed()
+}
+
+#[test]
+fn rate_limits_and_quotas() {
+    use std::time::Duration;
+
+    let client = Client::new(ClientConfig {
+        name: String::from("partner"),
+        key: String::from("secret"),
+        requests_per_minute: Some(2),
+        daily_quota: Some(3),
+    });
+    let now = Instant::now();
+    let today = NaiveDate::from_ymd(2019, 10, 1);
+    assert_eq!(client.admit_at(Api::Rest, now, today), Ok(()));
+    assert_eq!(client.admit_at(Api::Rest, now, today), Ok(()));
+    assert_eq!(
+        client.admit_at(Api::Rest, now, today),
+        Err(Denied::RateLimited)
+    );
+
+    let later = now + Duration::from_secs(30);
+    assert_eq!(client.admit_at(Api::GraphQL, later, today), Ok(()));
+    let much_later = now + Duration::from_secs(120);
+    assert_eq!(
+        client.admit_at(Api::Rest, much_later, today),
+        Err(Denied::QuotaExceeded)
+    );
+    assert_eq!(client.admit_at(Api::Rest, much_later, today.succ()), Ok(()));
+
+    let usage = client.usage();
+    assert_eq!(usage.requests.get("rest"), Some(&3));
+    assert_eq!(usage.rate_limited, 1);
+    assert_eq!(usage.quota_exceeded, 1);
+
+    assert!(constant_time_eq(b"secret", b"secret"));
+    assert!(!constant_time_eq(b"secret", b"secreT"));
+    assert!(!constant_time_eq(b"secret", b"secrets"));
+}
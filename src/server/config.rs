@@ -0,0 +1,512 @@
+use crate::{
+    client::cache,
+    server::poller::{PollIntervals, DEFAULT_ADVISORY_INTERVAL, DEFAULT_ETD_INTERVAL},
+};
+use anyhow::{anyhow, Context, Result};
+use log::LevelFilter;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+use structopt::StructOpt;
+
+pub const DEFAULT_BIND: &str = "127.0.0.1:3030";
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_MAX_QUERY_DEPTH: usize = 12;
+pub const DEFAULT_MAX_QUERY_COST: u32 = 150;
+pub const DEFAULT_WEBHOOK_RETRIES: u32 = 5;
+
+pub const API_GRAPHQL: &str = "graphql";
+pub const API_REST: &str = "rest";
+pub const API_SSE: &str = "sse";
+pub const API_MIRROR: &str = "mirror";
+pub const API_OPENAPI: &str = "openapi";
+pub const API_WEBHOOKS: &str = "webhooks";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Api {
+    GraphQL,
+    Rest,
+    Sse,
+    Mirror,
+    OpenApi,
+    Webhooks,
+}
+
+pub const MODE_SERVICE: &str = "service";
+pub const MODE_EXPORTER: &str = "exporter";
+
+// The service serves the enabled APIs, the exporter only serves live transit data as metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Service,
+    Exporter,
+}
+
+impl Mode {
+    pub fn from_code<T: AsRef<str>>(code: T) -> Result<Mode> {
+        match code.as_ref().trim().to_lowercase().as_ref() {
+            MODE_SERVICE => Ok(Mode::Service),
+            MODE_EXPORTER => Ok(Mode::Exporter),
+            code => Err(anyhow!("`{}` does not match any mode", code)),
+        }
+    }
+
+    pub fn to_code(&self) -> &'static str {
+        match self {
+            Mode::Service => MODE_SERVICE,
+            Mode::Exporter => MODE_EXPORTER,
+        }
+    }
+}
+
+pub const APIS: [Api; 6] = [
+    Api::GraphQL,
+    Api::Rest,
+    Api::Sse,
+    Api::Mirror,
+    Api::OpenApi,
+    Api::Webhooks,
+];
+// Webhooks have the server call out to any URL it is given, so they are only served when asked for
+pub const DEFAULT_APIS: [Api; 5] = [Api::GraphQL, Api::Rest, Api::Sse, Api::Mirror, Api::OpenApi];
+
+impl Api {
+    pub fn from_code<T: AsRef<str>>(code: T) -> Result<Api> {
+        match code.as_ref().trim().to_lowercase().as_ref() {
+            API_GRAPHQL => Ok(Api::GraphQL),
+            API_REST => Ok(Api::Rest),
+            API_SSE => Ok(Api::Sse),
+            API_MIRROR => Ok(Api::Mirror),
+            API_OPENAPI => Ok(Api::OpenApi),
+            API_WEBHOOKS => Ok(Api::Webhooks),
+            code => Err(anyhow!("`{}` does not match any API", code)),
+        }
+    }
+
+    pub fn to_code(&self) -> &'static str {
+        match self {
+            Api::GraphQL => API_GRAPHQL,
+            Api::Rest => API_REST,
+            Api::Sse => API_SSE,
+            Api::Mirror => API_MIRROR,
+            Api::OpenApi => API_OPENAPI,
+            Api::Webhooks => API_WEBHOOKS,
+        }
+    }
+}
+
+// Every flag can also be set through its environment variable or the config file, in that order
+// of precedence
+#[derive(Debug, Default, StructOpt)]
+#[structopt(name = "sfbart", about = "Real-time service for BART APIs")]
+pub struct Args {
+    #[structopt(
+        long,
+        env = "SFBART_CONFIG",
+        help = "Path to a TOML config file",
+        parse(from_os_str)
+    )]
+    pub config: Option<PathBuf>,
+    #[structopt(
+        long,
+        env = "SFBART_MODE",
+        help = "Either service or exporter [default: service]"
+    )]
+    pub mode: Option<String>,
+    #[structopt(
+        long,
+        env = "SFBART_BIND",
+        help = "Address to listen on [default: 127.0.0.1:3030]"
+    )]
+    pub bind: Option<String>,
+    #[structopt(
+        long = "key",
+        env = "SFBART_KEYS",
+        help = "BART API keys to spread calls over, the public key is used when none are given",
+        use_delimiter = true
+    )]
+    pub keys: Option<Vec<String>>,
+    #[structopt(
+        long,
+        env = "SFBART_ETD_INTERVAL",
+        help = "Seconds between polls of real-time departures"
+    )]
+    pub etd_interval: Option<u64>,
+    #[structopt(
+        long,
+        env = "SFBART_BSA_INTERVAL",
+        help = "Seconds between polls of service advisories"
+    )]
+    pub bsa_interval: Option<u64>,
+    #[structopt(
+        long,
+        env = "SFBART_ELEV_INTERVAL",
+        help = "Seconds between polls of elevator outages"
+    )]
+    pub elev_interval: Option<u64>,
+    #[structopt(
+        long,
+        env = "SFBART_COUNT_INTERVAL",
+        help = "Seconds between polls of the number of trains in service"
+    )]
+    pub count_interval: Option<u64>,
+    #[structopt(
+        long,
+        env = "SFBART_CACHE_CAPACITY",
+        help = "Maximum number of cached BART responses"
+    )]
+    pub cache_capacity: Option<usize>,
+    #[structopt(
+        long = "api",
+        env = "SFBART_APIS",
+        help = "APIs to serve, any of graphql, rest, sse, mirror, openapi and webhooks",
+        use_delimiter = true
+    )]
+    pub apis: Option<Vec<String>>,
+    #[structopt(
+        long,
+        env = "SFBART_LOG_LEVEL",
+        help = "One of off, error, warn, info, debug and trace [default: info]"
+    )]
+    pub log_level: Option<String>,
+    #[structopt(
+        long,
+        env = "SFBART_SHUTDOWN_TIMEOUT",
+        help = "Seconds to drain open connections for before exiting [default: 10]"
+    )]
+    pub shutdown_timeout: Option<u64>,
+    #[structopt(
+        long,
+        env = "SFBART_MAX_QUERY_DEPTH",
+        help = "Deepest nesting of fields a GraphQL query may have [default: 12]"
+    )]
+    pub max_query_depth: Option<usize>,
+    #[structopt(
+        long,
+        env = "SFBART_MAX_QUERY_COST",
+        help = "Highest total field cost a GraphQL query may have [default: 150]"
+    )]
+    pub max_query_cost: Option<u32>,
+    #[structopt(
+        long,
+        env = "SFBART_WEBHOOK_RETRIES",
+        help = "Times to retry a webhook delivery before dead-lettering it [default: 5]"
+    )]
+    pub webhook_retries: Option<u32>,
+    #[structopt(
+        long,
+        env = "SFBART_WEBHOOK_DEAD_LETTERS",
+        help = "File to append webhook deliveries that ran out of retries to, as JSON lines",
+        parse(from_os_str)
+    )]
+    pub webhook_dead_letters: Option<PathBuf>,
+    #[structopt(
+        long = "webhook-allowed-host",
+        env = "SFBART_WEBHOOK_ALLOWED_HOSTS",
+        help = "Hosts webhooks may be delivered to even though they are loopback, private or link-local",
+        use_delimiter = true
+    )]
+    pub webhook_allowed_hosts: Option<Vec<String>>,
+}
+
+// A partner allowed to call the server, only configurable in the file as `[[clients]]`. Without
+// any, the server is open to everyone.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    pub name: String,
+    pub key: String,
+    pub requests_per_minute: Option<u32>,
+    pub daily_quota: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub mode: Option<String>,
+    pub bind: Option<String>,
+    pub keys: Option<Vec<String>>,
+    pub etd_interval: Option<u64>,
+    pub bsa_interval: Option<u64>,
+    pub elev_interval: Option<u64>,
+    pub count_interval: Option<u64>,
+    pub cache_capacity: Option<usize>,
+    pub apis: Option<Vec<String>>,
+    pub log_level: Option<String>,
+    pub shutdown_timeout: Option<u64>,
+    pub max_query_depth: Option<usize>,
+    pub max_query_cost: Option<u32>,
+    pub webhook_retries: Option<u32>,
+    pub webhook_dead_letters: Option<PathBuf>,
+    pub webhook_allowed_hosts: Option<Vec<String>>,
+    // Only settable in the file, as a table of `"Type.field" = cost`
+    pub field_costs: Option<HashMap<String, u32>>,
+    pub clients: Option<Vec<ClientConfig>>,
+}
+
+impl FileConfig {
+    pub fn read(path: &PathBuf) -> Result<FileConfig> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Could not read `{}`", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config in `{}`", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub mode: Mode,
+    pub bind: SocketAddr,
+    pub keys: Vec<String>,
+    pub intervals: PollIntervals,
+    pub cache_capacity: usize,
+    pub apis: HashSet<Api>,
+    pub log_level: LevelFilter,
+    pub shutdown_timeout: Duration,
+    pub max_query_depth: usize,
+    pub max_query_cost: u32,
+    pub field_costs: HashMap<String, u32>,
+    pub clients: Vec<ClientConfig>,
+    pub webhook_retries: u32,
+    pub webhook_dead_letters: Option<PathBuf>,
+    pub webhook_allowed_hosts: Vec<String>,
+}
+
+fn interval(name: &str, seconds: Option<u64>, default: Duration) -> Result<Duration> {
+    match seconds {
+        Some(0) => Err(anyhow!("`{}` must be at least 1 second", name)),
+        Some(seconds) => Ok(Duration::from_secs(seconds)),
+        None => Ok(default),
+    }
+}
+
+impl Config {
+    // Reads the command line, environment and config file
+    pub fn load() -> Result<Config> {
+        let args = Args::from_args();
+        let file = match &args.config {
+            Some(path) => FileConfig::read(path)?,
+            None => FileConfig::default(),
+        };
+        Config::from_sources(args, file)
+    }
+
+    pub fn from_sources(args: Args, file: FileConfig) -> Result<Config> {
+        let mode = match args.mode.or(file.mode) {
+            Some(mode) => Mode::from_code(mode)?,
+            None => Mode::Service,
+        };
+        #[cfg(not(feature = "metrics"))]
+        {
+            if mode == Mode::Exporter {
+                return Err(anyhow!(
+                    "`mode = \"exporter\"` requires the `metrics` feature"
+                ));
+            }
+        }
+
+        let bind = args
+            .bind
+            .or(file.bind)
+            .unwrap_or_else(|| String::from(DEFAULT_BIND));
+        let bind = SocketAddr::from_str(&bind)
+            .with_context(|| format!("`bind` is not a socket address: `{}`", bind))?;
+
+        let keys = args.keys.or(file.keys).unwrap_or_default();
+        if keys.iter().any(|key| key.trim().is_empty()) {
+            return Err(anyhow!("`keys` cannot contain an empty key"));
+        }
+
+        let intervals = PollIntervals {
+            etd: interval(
+                "etd_interval",
+                args.etd_interval.or(file.etd_interval),
+                DEFAULT_ETD_INTERVAL,
+            )?,
+            bsa: interval(
+                "bsa_interval",
+                args.bsa_interval.or(file.bsa_interval),
+                DEFAULT_ADVISORY_INTERVAL,
+            )?,
+            elev: interval(
+                "elev_interval",
+                args.elev_interval.or(file.elev_interval),
+                DEFAULT_ADVISORY_INTERVAL,
+            )?,
+            count: interval(
+                "count_interval",
+                args.count_interval.or(file.count_interval),
+                DEFAULT_ADVISORY_INTERVAL,
+            )?,
+        };
+
+        let cache_capacity = args
+            .cache_capacity
+            .or(file.cache_capacity)
+            .unwrap_or(cache::DEFAULT_CAPACITY);
+        if cache_capacity == 0 {
+            return Err(anyhow!("`cache_capacity` must be at least 1"));
+        }
+
+        let apis = match args.apis.or(file.apis) {
+            Some(codes) => codes
+                .iter()
+                .map(Api::from_code)
+                .collect::<Result<HashSet<Api>>>()?,
+            None => DEFAULT_APIS.iter().cloned().collect(),
+        };
+        if apis.is_empty() {
+            return Err(anyhow!("`apis` must enable at least one API"));
+        }
+        #[cfg(not(feature = "webhooks"))]
+        {
+            if apis.contains(&Api::Webhooks) {
+                return Err(anyhow!("`webhooks` requires the `webhooks` feature"));
+            }
+        }
+
+        let log_level = match args.log_level.or(file.log_level) {
+            Some(level) => LevelFilter::from_str(&level)
+                .map_err(|_| anyhow!("`log_level` is not a log level: `{}`", level))?,
+            None => DEFAULT_LOG_LEVEL,
+        };
+
+        let shutdown_timeout = interval(
+            "shutdown_timeout",
+            args.shutdown_timeout.or(file.shutdown_timeout),
+            DEFAULT_SHUTDOWN_TIMEOUT,
+        )?;
+
+        let max_query_depth = args
+            .max_query_depth
+            .or(file.max_query_depth)
+            .unwrap_or(DEFAULT_MAX_QUERY_DEPTH);
+        if max_query_depth == 0 {
+            return Err(anyhow!("`max_query_depth` must be at least 1"));
+        }
+        let max_query_cost = args
+            .max_query_cost
+            .or(file.max_query_cost)
+            .unwrap_or(DEFAULT_MAX_QUERY_COST);
+        let field_costs = file.field_costs.unwrap_or_default();
+        if let Some(field) = field_costs
+            .keys()
+            .find(|field| field.split('.').filter(|part| !part.is_empty()).count() != 2)
+        {
+            return Err(anyhow!(
+                "`field_costs` keys must look like `Type.field`: `{}`",
+                field
+            ));
+        }
+        #[cfg(feature = "graphql")]
+        crate::server::graphql::complexity::check_costs(
+            &crate::server::graphql::schema(),
+            &field_costs,
+        )?;
+
+        let clients = file.clients.unwrap_or_default();
+        let mut names = HashSet::new();
+        let mut client_keys = HashSet::new();
+        for client in &clients {
+            if client.name.trim().is_empty() || client.key.trim().is_empty() {
+                return Err(anyhow!("`clients` need a name and a key"));
+            }
+            if !names.insert(&client.name) || !client_keys.insert(&client.key) {
+                return Err(anyhow!(
+                    "`clients` names and keys must be unique: `{}`",
+                    client.name
+                ));
+            }
+            if client.requests_per_minute == Some(0) {
+                return Err(anyhow!(
+                    "`requests_per_minute` of `{}` must be at least 1",
+                    client.name
+                ));
+            }
+        }
+
+        let webhook_retries = args
+            .webhook_retries
+            .or(file.webhook_retries)
+            .unwrap_or(DEFAULT_WEBHOOK_RETRIES);
+        let webhook_dead_letters = args.webhook_dead_letters.or(file.webhook_dead_letters);
+        let webhook_allowed_hosts: Vec<String> = args
+            .webhook_allowed_hosts
+            .or(file.webhook_allowed_hosts)
+            .unwrap_or_default()
+            .iter()
+            .map(|host| host.trim().to_lowercase())
+            .collect();
+        if webhook_allowed_hosts.iter().any(String::is_empty) {
+            return Err(anyhow!(
+                "`webhook_allowed_hosts` cannot contain an empty host"
+            ));
+        }
+
+        Ok(Config {
+            mode,
+            bind,
+            keys,
+            intervals,
+            cache_capacity,
+            apis,
+            log_level,
+            shutdown_timeout,
+            max_query_depth,
+            max_query_cost,
+            field_costs,
+            clients,
+            webhook_retries,
+            webhook_dead_letters,
+            webhook_allowed_hosts,
+        })
+    }
+
+    // The key the server calls BART with
+    pub fn key(&self) -> Option<String> {
+        self.keys.first().cloned()
+    }
+
+    pub fn enabled(&self, api: Api) -> bool {
+        self.apis.contains(&api)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::from_sources(Args::default(), FileConfig::default()).unwrap()
+    }
+}
+
+#[test]
+fn config_precedence() {
+    let file: FileConfig = toml::from_str(
+        r#"
+        bind = "0.0.0.0:8080"
+        etd_interval = 30
+        apis = ["graphql"]
+        "#,
+    )
+    .unwrap();
+    let args = Args {
+        etd_interval: Some(5),
+        ..Args::default()
+    };
+    let config = Config::from_sources(args, file).unwrap();
+    assert_eq!(config.bind, SocketAddr::from(([0, 0, 0, 0], 8080)));
+    assert_eq!(config.intervals.etd, Duration::from_secs(5));
+    assert!(config.enabled(Api::GraphQL));
+    assert!(!config.enabled(Api::Rest));
+
+    let args = Args {
+        etd_interval: Some(0),
+        ..Args::default()
+    };
+    assert!(Config::from_sources(args, FileConfig::default()).is_err());
+}
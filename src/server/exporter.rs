@@ -0,0 +1,214 @@
+use crate::{
+    client::apis::{
+        advisories::{
+            bsa::BsaResponse,
+            count::{self, Count},
+            elev::ElevResponse,
+        },
+        real_time_estimates::etd::{EtdEstimate, EtdOptions, EtdResponse},
+    },
+    server::{
+        config::Config,
+        poller::{poll_changes, Registry},
+        shutdown::Shutdown,
+        Routes,
+    },
+};
+use futures::{future, pin_mut, StreamExt};
+use prometheus::{Encoder, GaugeVec, IntGauge, Opts, Registry as MetricsRegistry, TextEncoder};
+use std::{collections::HashMap, sync::Arc};
+use warp::{http::StatusCode, Filter, Reply};
+
+pub const COUNT_POLLER: &str = "count";
+
+// BART's own state as metrics, kept in a registry of its own so it never mixes with the
+// service's health metrics
+pub struct Exporter {
+    registry: MetricsRegistry,
+    departure_minutes: GaugeVec,
+    departure_delay: GaugeVec,
+    departure_length: GaugeVec,
+    trains_in_service: IntGauge,
+    advisories: IntGauge,
+    elevator_outages: IntGauge,
+}
+
+impl Exporter {
+    pub fn new() -> prometheus::Result<Exporter> {
+        let labels = &["station", "direction", "color"];
+        let departure_minutes = GaugeVec::new(
+            Opts::new(
+                "bart_departure_minutes",
+                "Minutes until the next train, by station, direction and color",
+            ),
+            labels,
+        )?;
+        let departure_delay = GaugeVec::new(
+            Opts::new(
+                "bart_departure_delay_seconds",
+                "Delay of the next train, by station, direction and color",
+            ),
+            labels,
+        )?;
+        let departure_length = GaugeVec::new(
+            Opts::new(
+                "bart_departure_length_cars",
+                "Cars in the next train, by station, direction and color",
+            ),
+            labels,
+        )?;
+        let trains_in_service =
+            IntGauge::new("bart_trains_in_service", "Trains currently in service")?;
+        let advisories = IntGauge::new("bart_advisories_active", "Active service advisories")?;
+        let elevator_outages =
+            IntGauge::new("bart_elevator_outages_active", "Active elevator outages")?;
+
+        let registry = MetricsRegistry::new();
+        registry.register(Box::new(departure_minutes.clone()))?;
+        registry.register(Box::new(departure_delay.clone()))?;
+        registry.register(Box::new(departure_length.clone()))?;
+        registry.register(Box::new(trains_in_service.clone()))?;
+        registry.register(Box::new(advisories.clone()))?;
+        registry.register(Box::new(elevator_outages.clone()))?;
+
+        Ok(Exporter {
+            registry,
+            departure_minutes,
+            departure_delay,
+            departure_length,
+            trains_in_service,
+            advisories,
+            elevator_outages,
+        })
+    }
+
+    // Only the soonest train for each station, direction and color is reported
+    pub fn observe_etd(&self, response: &EtdResponse) {
+        let mut next = HashMap::new();
+        for station in &response.station {
+            for etd in &station.etd {
+                for estimate in &etd.estimate {
+                    let labels = (
+                        station.abbr.to_abbr(),
+                        estimate.direction.to_code(),
+                        estimate.color.to_full(),
+                    );
+                    let sooner = next.get(&labels).map_or(true, |current: &&EtdEstimate| {
+                        estimate.minutes.to_number() < current.minutes.to_number()
+                    });
+                    if sooner {
+                        next.insert(labels, estimate);
+                    }
+                }
+            }
+        }
+
+        self.departure_minutes.reset();
+        self.departure_delay.reset();
+        self.departure_length.reset();
+        for ((station, direction, color), estimate) in next {
+            let labels = &[station, direction, color];
+            self.departure_minutes
+                .with_label_values(labels)
+                .set(f64::from(estimate.minutes.to_number()));
+            self.departure_delay
+                .with_label_values(labels)
+                .set(f64::from(estimate.delay));
+            self.departure_length
+                .with_label_values(labels)
+                .set(f64::from(estimate.length));
+        }
+    }
+
+    pub fn observe_count(&self, count: &Count) {
+        self.trains_in_service.set(i64::from(count.traincount));
+    }
+
+    // BART answers with a single placeholder entry without an id when nothing is going on
+    pub fn observe_bsa(&self, response: &BsaResponse) {
+        let active = response.bsa.iter().filter(|bsa| bsa.id.is_some()).count();
+        self.advisories.set(active as i64);
+    }
+
+    pub fn observe_elev(&self, response: &ElevResponse) {
+        let active = response.bsa.iter().filter(|elev| elev.id.is_some()).count();
+        self.elevator_outages.set(active as i64);
+    }
+
+    pub fn gather(&self) -> prometheus::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+// Runs until the subscriptions are dropped, which is never for the exporter
+pub async fn run(exporter: Arc<Exporter>, pollers: Arc<Registry>, config: &Config) {
+    let key = config.key();
+    let counts = poll_changes(
+        String::from(COUNT_POLLER),
+        config.intervals.count,
+        move || {
+            let key = key.clone();
+            async move { count::call(key).await }
+        },
+        |count: &Count| count.traincount,
+    );
+
+    let etd = pollers.etd(EtdOptions::OriginAll).for_each(|response| {
+        exporter.observe_etd(&response);
+        future::ready(())
+    });
+    let bsa = pollers.bsa().for_each(|response| {
+        exporter.observe_bsa(&response);
+        future::ready(())
+    });
+    let elev = pollers.elev().for_each(|response| {
+        exporter.observe_elev(&response);
+        future::ready(())
+    });
+    let count = counts.for_each(|count| {
+        exporter.observe_count(&count);
+        future::ready(())
+    });
+    futures::join!(etd, bsa, elev, count);
+}
+
+pub fn routes(exporter: Arc<Exporter>) -> Routes {
+    warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get2())
+        .map(move || match exporter.gather() {
+            Ok(buffer) => Box::new(warp::reply::with_header(
+                buffer,
+                "content-type",
+                TextEncoder::new().format_type(),
+            )) as Box<dyn Reply>,
+            Err(error) => Box::new(warp::reply::with_status(
+                error.to_string(),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )) as Box<dyn Reply>,
+        })
+        .boxed()
+}
+
+// The train count is polled here rather than by the shared pollers, so stopping those on shutdown
+// is not enough to stop `run`
+pub async fn serve(config: &Config, pollers: Arc<Registry>, shutdown: Shutdown) {
+    let exporter = Arc::new(Exporter::new().expect("Exporter metrics are valid"));
+    let (_, server) = warp::serve(routes(exporter.clone()))
+        .bind_with_graceful_shutdown(config.bind, shutdown.clone().wait());
+    let run = run(exporter, pollers, config);
+    let stopped = shutdown.wait();
+    pin_mut!(run, stopped);
+    futures::join!(server, future::select(run, stopped));
+}
+
+#[test]
+fn exporter_gathers_own_registry() {
+    let exporter = Exporter::new().unwrap();
+    exporter.trains_in_service.set(42);
+    let text = String::from_utf8(exporter.gather().unwrap()).unwrap();
+    assert!(text.contains("bart_trains_in_service 42"));
+    assert!(!text.contains("sfbart_"));
+}
@@ -0,0 +1,342 @@
+use super::Schema;
+use anyhow::{anyhow, Result};
+use graphql_parser::query::{
+    Definition, FragmentDefinition, OperationDefinition, Selection, SelectionSet, TypeCondition,
+};
+use juniper::{http::GraphQLRequest, Type};
+use serde::Deserialize;
+use std::{cmp, collections::HashMap, sync::Arc};
+
+const QUERY: &str = "Query";
+const MUTATION: &str = "Mutation";
+const SUBSCRIPTION: &str = "Subscription";
+// How many items a list is taken to have, BART's lists are rarely much longer
+pub const ESTIMATED_LIST_SIZE: u32 = 10;
+
+// Every root field makes one call to BART, or subscribes to one poller, as does every lookup
+// field for each item it is selected on. Everything else is already part of a response, so costs
+// nothing unless configured otherwise. Whatever is selected within a list costs as much again for
+// each item the list is estimated to have.
+pub fn default_costs() -> HashMap<String, u32> {
+    let fields = [
+        "Query.stations",
+        "Query.stationInfo",
+        "Query.stationAccess",
+        "Query.routes",
+        "Query.routeInfo",
+        "Query.etd",
+        "Query.arrive",
+        "Query.depart",
+        "Query.advisories",
+        "Query.elevatorStatus",
+        "Query.trainCount",
+        "Query.version",
+        "Subscription.departures",
+        "Subscription.advisories",
+        "Subscription.elevatorStatus",
+        "Etd.destinationStation",
+        "Etd.destinationAccess",
+        "Leg.route",
+    ];
+    fields
+        .iter()
+        .map(|field| (String::from(*field), 1))
+        .collect()
+}
+
+// The type `Type.field` returns, and whether it is a list of them
+fn field_type(schema: &Schema, type_name: &str, name: &str) -> Option<(String, bool)> {
+    let field = schema
+        .schema
+        .concrete_type_by_name(type_name)?
+        .field_by_name(name)?;
+    let list = match &field.field_type {
+        Type::List(..) | Type::NonNullList(..) => true,
+        _ => false,
+    };
+    Some((String::from(field.field_type.innermost_name()), list))
+}
+
+// Costs can only be set on fields the schema has, anything else is most likely a typo
+pub fn check_costs(schema: &Schema, costs: &HashMap<String, u32>) -> Result<()> {
+    for field in costs.keys() {
+        let mut parts = field.splitn(2, '.');
+        let known = match (parts.next(), parts.next()) {
+            (Some(type_name), Some(name)) => field_type(schema, type_name, name).is_some(),
+            _ => false,
+        };
+        if !known {
+            return Err(anyhow!(
+                "`field_costs` has `{}`, which is not a field of the GraphQL schema",
+                field
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Complexity {
+    pub depth: usize,
+    pub cost: u32,
+}
+
+impl Complexity {
+    fn max(self, other: Complexity) -> Complexity {
+        Complexity {
+            depth: cmp::max(self.depth, other.depth),
+            cost: cmp::max(self.cost, other.cost),
+        }
+    }
+
+    fn add(self, other: Complexity) -> Complexity {
+        Complexity {
+            depth: cmp::max(self.depth, other.depth),
+            cost: self.cost.saturating_add(other.cost),
+        }
+    }
+}
+
+// Costs are keyed by `Type.field`, e.g. `Query.etd`, and checked against the schema by
+// `check_costs` when the configuration is loaded
+pub struct Limits {
+    schema: Arc<Schema>,
+    max_depth: usize,
+    max_cost: u32,
+    costs: HashMap<String, u32>,
+}
+
+impl Limits {
+    pub fn new(
+        schema: Arc<Schema>,
+        max_depth: usize,
+        max_cost: u32,
+        overrides: &HashMap<String, u32>,
+    ) -> Limits {
+        let mut costs = default_costs();
+        costs.extend(overrides.iter().map(|(field, cost)| (field.clone(), *cost)));
+        Limits {
+            schema,
+            max_depth,
+            max_cost,
+            costs,
+        }
+    }
+
+    fn field_type(&self, type_name: &str, name: &str) -> Option<(String, bool)> {
+        field_type(&self.schema, type_name, name)
+    }
+
+    fn cost(&self, type_name: &str, name: &str) -> u32 {
+        self.costs
+            .get(&format!("{}.{}", type_name, name))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    // `None` when the query doesn't parse, which juniper reports better than we could
+    pub fn analyze(&self, query: &str, operation_name: Option<&str>) -> Option<Complexity> {
+        let document = graphql_parser::parse_query(query).ok()?;
+        let fragments = document
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => Some((fragment.name.as_str(), fragment)),
+                Definition::Operation(_) => None,
+            })
+            .collect();
+        let walker = Walker {
+            limits: self,
+            fragments,
+        };
+
+        let operations = document
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Operation(operation) => Some(operation),
+                Definition::Fragment(_) => None,
+            })
+            .filter_map(|operation| match operation {
+                OperationDefinition::SelectionSet(selection_set) => {
+                    Some((None, QUERY, selection_set))
+                }
+                OperationDefinition::Query(query) => {
+                    Some((query.name.as_ref(), QUERY, &query.selection_set))
+                }
+                OperationDefinition::Mutation(mutation) => {
+                    Some((mutation.name.as_ref(), MUTATION, &mutation.selection_set))
+                }
+                OperationDefinition::Subscription(subscription) => Some((
+                    subscription.name.as_ref(),
+                    SUBSCRIPTION,
+                    &subscription.selection_set,
+                )),
+            })
+            .filter(|(name, _, _)| match operation_name {
+                Some(operation_name) => name.map(String::as_str) == Some(operation_name),
+                None => true,
+            });
+
+        let mut complexity = Complexity::default();
+        for (_, root, selection_set) in operations {
+            let operation = walker.selection_set(root, selection_set, &mut Vec::new());
+            complexity = complexity.max(operation);
+        }
+        Some(complexity)
+    }
+
+    // Rejects queries over either limit before anything is executed
+    pub fn check(&self, query: &str, operation_name: Option<&str>) -> Result<()> {
+        let complexity = match self.analyze(query, operation_name) {
+            Some(complexity) => complexity,
+            None => return Ok(()),
+        };
+        if complexity.depth > self.max_depth {
+            return Err(anyhow!(
+                "Query is nested {} levels deep, more than the limit of {}",
+                complexity.depth,
+                self.max_depth
+            ));
+        }
+        if complexity.cost > self.max_cost {
+            return Err(anyhow!(
+                "Query costs {}, more than the limit of {}",
+                complexity.cost,
+                self.max_cost
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_request(&self, request: &GraphQLRequest) -> Result<()> {
+        // `GraphQLRequest` doesn't give its query out other than through serde
+        let request: RequestText = serde_json::from_value(serde_json::to_value(request)?)?;
+        self.check(
+            &request.query,
+            request.operation_name.as_ref().map(String::as_str),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct RequestText {
+    query: String,
+    #[serde(rename = "operationName")]
+    operation_name: Option<String>,
+}
+
+struct Walker<'a> {
+    limits: &'a Limits,
+    fragments: HashMap<&'a str, &'a FragmentDefinition>,
+}
+
+impl<'a> Walker<'a> {
+    // Fragments spread within themselves are only counted once, juniper rejects them anyway
+    fn selection_set(
+        &self,
+        type_name: &str,
+        selection_set: &'a SelectionSet,
+        spreads: &mut Vec<&'a str>,
+    ) -> Complexity {
+        let mut complexity = Complexity::default();
+        for selection in &selection_set.items {
+            let selected = match selection {
+                Selection::Field(field) => {
+                    if field.name.starts_with("__") {
+                        continue;
+                    }
+                    let children = if field.selection_set.items.is_empty() {
+                        Complexity::default()
+                    } else {
+                        let (field_type, list) = self
+                            .limits
+                            .field_type(type_name, &field.name)
+                            .unwrap_or_default();
+                        let children =
+                            self.selection_set(&field_type, &field.selection_set, spreads);
+                        if list {
+                            Complexity {
+                                cost: children.cost.saturating_mul(ESTIMATED_LIST_SIZE),
+                                ..children
+                            }
+                        } else {
+                            children
+                        }
+                    };
+                    Complexity {
+                        depth: children.depth + 1,
+                        cost: children
+                            .cost
+                            .saturating_add(self.limits.cost(type_name, &field.name)),
+                    }
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.fragment_name.as_str();
+                    match self.fragments.get(name) {
+                        Some(fragment) if !spreads.contains(&name) => {
+                            let TypeCondition::On(on) = &fragment.type_condition;
+                            spreads.push(name);
+                            let selected = self.selection_set(on, &fragment.selection_set, spreads);
+                            spreads.pop();
+                            selected
+                        }
+                        _ => Complexity::default(),
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    let on = match &fragment.type_condition {
+                        Some(TypeCondition::On(on)) => on.as_str(),
+                        None => type_name,
+                    };
+                    self.selection_set(on, &fragment.selection_set, spreads)
+                }
+            };
+            complexity = complexity.add(selected);
+        }
+        complexity
+    }
+}
+
+#[test]
+fn limits_reject_expensive_queries() {
+    let limits = Limits::new(Arc::new(super::schema()), 4, 2, &HashMap::new());
+    assert_eq!(
+        limits.analyze("{ version { apiVersion } }", None),
+        Some(Complexity { depth: 2, cost: 1 })
+    );
+    assert!(limits
+        .check(
+            "{ a: version { apiVersion } ...counts } fragment counts on Query { trainCount { traincount } }",
+            None
+        )
+        .is_ok());
+    assert!(limits
+        .check(
+            "{ a: version { apiVersion } b: version { apiVersion } c: trainCount { traincount } }",
+            None
+        )
+        .is_err());
+    assert!(limits
+        .check("{ etd { station { etd { estimate { minutes } } } } }", None)
+        .is_err());
+
+    // One lookup for each departure of each station
+    let limits = Limits::new(Arc::new(super::schema()), 12, 200, &HashMap::new());
+    assert_eq!(
+        limits.analyze(
+            "{ etd { station { etd { destinationStation { name } } } } }",
+            None
+        ),
+        Some(Complexity {
+            depth: 5,
+            cost: 1 + ESTIMATED_LIST_SIZE * ESTIMATED_LIST_SIZE
+        })
+    );
+
+    let mut costs = HashMap::new();
+    costs.insert(String::from("Query.etd"), 2);
+    assert!(check_costs(&super::schema(), &costs).is_ok());
+    costs.insert(String::from("Query.etds"), 2);
+    assert!(check_costs(&super::schema(), &costs).is_err());
+}
@@ -0,0 +1,149 @@
+use crate::client::{
+    apis::{
+        route_information::routeinfo::{self, Route},
+        station_information::{stnaccess, stninfo},
+    },
+    constants::station::Station,
+};
+use anyhow::Result;
+use futures::future::{BoxFuture, Future, FutureExt, Shared};
+use juniper::{FieldError, FieldResult};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+// Errors are kept as strings, since a shared result has to be `Clone`
+type Loaded<V> = Shared<BoxFuture<'static, Result<Arc<V>, String>>>;
+
+// Loads each key at most once, however many fields ask for it and however concurrently. BART has
+// no batch endpoints, so every distinct key is still one call.
+pub struct Loader<K, V> {
+    loaded: Mutex<HashMap<K, Loaded<V>>>,
+}
+
+impl<K, V> Loader<K, V>
+where
+    K: Eq + Hash,
+    V: Send + Sync + 'static,
+{
+    pub fn new() -> Loader<K, V> {
+        Loader {
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // `fetch` is only called the first time `key` is loaded
+    pub async fn load<F, Fut>(&self, key: K, fetch: F) -> FieldResult<Arc<V>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>> + Send + 'static,
+    {
+        let loaded = self
+            .loaded
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| {
+                fetch()
+                    .map(|result| result.map(Arc::new).map_err(|error| format!("{:#}", error)))
+                    .boxed()
+                    .shared()
+            })
+            .clone();
+        loaded.await.map_err(FieldError::from)
+    }
+
+    pub fn len(&self) -> usize {
+        self.loaded.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V> Default for Loader<K, V>
+where
+    K: Eq + Hash,
+    V: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Loader::new()
+    }
+}
+
+// Created for every GraphQL request, so lookups are only shared within one response
+pub struct Loaders {
+    key: Option<String>,
+    stninfo: Loader<Station, stninfo::Station>,
+    stnaccess: Loader<Station, stnaccess::Station>,
+    routeinfo: Loader<u8, Route>,
+}
+
+impl Loaders {
+    pub fn new(key: Option<String>) -> Loaders {
+        Loaders {
+            key,
+            stninfo: Loader::new(),
+            stnaccess: Loader::new(),
+            routeinfo: Loader::new(),
+        }
+    }
+
+    pub async fn station_info(&self, station: Station) -> FieldResult<Arc<stninfo::Station>> {
+        let key = self.key.clone();
+        self.stninfo
+            .load(station.clone(), move || async move {
+                Ok(stninfo::call(station, key).await?.stations.station)
+            })
+            .await
+    }
+
+    pub async fn station_access(&self, station: Station) -> FieldResult<Arc<stnaccess::Station>> {
+        let key = self.key.clone();
+        self.stnaccess
+            .load(station.clone(), move || async move {
+                Ok(stnaccess::call(station, key).await?.stations.station)
+            })
+            .await
+    }
+
+    // Always the route as currently scheduled
+    pub async fn route_info(&self, route: u8) -> FieldResult<Arc<Route>> {
+        let key = self.key.clone();
+        self.routeinfo
+            .load(route, move || async move {
+                Ok(routeinfo::call(route, &None, key).await?.routes.route)
+            })
+            .await
+    }
+}
+
+impl Default for Loaders {
+    fn default() -> Self {
+        Loaders::new(None)
+    }
+}
+
+#[tokio::test]
+async fn loader_fetches_each_key_once() {
+    use futures::future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let loader = Loader::<&str, usize>::new();
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetch = || {
+        let fetches = fetches.clone();
+        async move { Ok(fetches.fetch_add(1, Ordering::SeqCst)) }
+    };
+
+    let (first, second) =
+        future::join(loader.load("mont", fetch), loader.load("mont", fetch)).await;
+    assert_eq!(*first.unwrap(), 0);
+    assert_eq!(*second.unwrap(), 0);
+    assert_eq!(*loader.load("embr", fetch).await.unwrap(), 1);
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    assert_eq!(loader.len(), 2);
+}
@@ -0,0 +1,163 @@
+pub mod complexity;
+pub mod loader;
+pub mod objects;
+pub mod query;
+pub mod scalars;
+pub mod subscription;
+pub mod ws;
+
+use self::{
+    complexity::Limits,
+    loader::Loaders,
+    query::Query,
+    subscription::Subscription,
+    ws::{protocol::Protocol, Services},
+};
+use crate::server::{auth::API_KEY_HEADER, poller::Registry};
+use juniper::{
+    http::{graphiql::graphiql_source, GraphQLRequest},
+    DefaultScalarValue, EmptyMutation, RootNode,
+};
+use std::sync::Arc;
+use warp::{filters::BoxedFilter, http::StatusCode, Filter, Reply};
+
+#[derive(Clone)]
+pub struct Context {
+    pub key: Option<String>,
+    pub pollers: Arc<Registry>,
+    pub loaders: Arc<Loaders>,
+}
+
+impl Context {
+    pub fn new(key: Option<String>, pollers: Arc<Registry>) -> Context {
+        Context {
+            loaders: Arc::new(Loaders::new(key.clone())),
+            key,
+            pollers,
+        }
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_ref().map(String::as_str)
+    }
+
+    // Lookups are only shared within a single request or subscription
+    pub fn for_request(&self) -> Context {
+        Context::new(self.key.clone(), self.pollers.clone())
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context::new(None, Arc::new(Registry::default()))
+    }
+}
+
+impl juniper::Context for Context {}
+
+pub type Schema = RootNode<'static, Query, EmptyMutation<Context>, Subscription>;
+
+pub type Coordinator = juniper_subscriptions::Coordinator<
+    'static,
+    Query,
+    EmptyMutation<Context>,
+    Subscription,
+    Context,
+    DefaultScalarValue,
+>;
+
+pub fn schema() -> Schema {
+    Schema::new(Query, EmptyMutation::new(), Subscription)
+}
+
+pub fn coordinator() -> Coordinator {
+    Coordinator::new(schema())
+}
+
+// Queries over HTTP, which clients are charged for per request
+pub fn routes(
+    schema: Arc<Schema>,
+    context: Context,
+    limits: Arc<Limits>,
+) -> BoxedFilter<(Box<dyn Reply>,)> {
+    let context = warp::any().map(move || context.for_request());
+
+    warp::path("graphql")
+        .and(warp::path::end())
+        .and(warp::post2())
+        .and(warp::body::json())
+        .and(context.clone())
+        .and_then(move |request: GraphQLRequest, context: Context| {
+            let schema = schema.clone();
+            let checked = limits.check_request(&request);
+            async move {
+                if let Err(error) = checked {
+                    let errors =
+                        serde_json::json!({ "errors": [{ "message": error.to_string() }] });
+                    let reply = warp::reply::with_status(
+                        warp::reply::json(&errors),
+                        StatusCode::BAD_REQUEST,
+                    );
+                    return Ok::<_, warp::Rejection>(Box::new(reply) as Box<dyn Reply>);
+                }
+                let response = request.execute_async(&schema, &context).await;
+                let status = if response.is_ok() {
+                    StatusCode::OK
+                } else {
+                    StatusCode::BAD_REQUEST
+                };
+                let reply = warp::reply::with_status(warp::reply::json(&response), status);
+                Ok::<_, warp::Rejection>(Box::new(reply) as Box<dyn Reply>)
+            }
+        })
+        .boxed()
+}
+
+// Websockets, which authenticate and are charged when they initialize, and GraphiQL
+pub fn subscriptions(context: Context, services: Services) -> BoxedFilter<(Box<dyn Reply>,)> {
+    let context = warp::any().map(move || context.for_request());
+
+    let subscriptions = warp::path("graphql")
+        .and(warp::path::end())
+        .and(warp::ws2())
+        .and(warp::header::optional::<String>("sec-websocket-protocol"))
+        .and(warp::header::optional::<String>(API_KEY_HEADER))
+        .and(context)
+        .map(
+            move |ws: warp::ws::Ws2,
+                  requested: Option<String>,
+                  api_key: Option<String>,
+                  context: Context| {
+                let protocol = match Protocol::negotiate(requested.as_ref().map(String::as_str)) {
+                    Some(protocol) => protocol,
+                    None => {
+                        let reply = warp::reply::with_status(
+                            "Unsupported websocket subprotocol",
+                            StatusCode::BAD_REQUEST,
+                        );
+                        return Box::new(reply) as Box<dyn Reply>;
+                    }
+                };
+                let services = services.clone();
+                let reply = ws.on_upgrade(move |websocket| {
+                    ws::serve(websocket, protocol, api_key, context, services)
+                });
+                // Only echo the subprotocol back when the client asked for one
+                match requested {
+                    Some(_) => Box::new(warp::reply::with_header(
+                        reply,
+                        "sec-websocket-protocol",
+                        protocol.name(),
+                    )) as Box<dyn Reply>,
+                    None => Box::new(reply) as Box<dyn Reply>,
+                }
+            },
+        );
+
+    let graphiql = warp::path("graphiql")
+        .and(warp::path::end())
+        .and(warp::get2())
+        .map(|| Box::new(warp::reply::html(graphiql_source("/graphql"))) as Box<dyn Reply>);
+
+    subscriptions.or(graphiql).unify().boxed()
+}
@@ -0,0 +1,128 @@
+use super::Context;
+use crate::client::{
+    apis::{
+        real_time_estimates::etd::{Etd, EtdEstimate},
+        route_information::routeinfo::Route,
+        schedule_information::arrive::Leg,
+        station_information::{stnaccess, stninfo},
+    },
+    constants::{
+        datetime::{Date, Time},
+        station::Station,
+    },
+};
+use juniper::{FieldError, FieldResult};
+
+// Objects with fields that look something else up, through the request's loaders so that a list
+// of them makes one call per distinct station or route
+
+#[juniper::object(Context = Context)]
+impl Etd {
+    // Does not always exactly match an acutal station name (e.g. "Warm Springs" instead of "Warm
+    // Springs/South Fremont")
+    fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    fn abbreviation(&self) -> &Station {
+        &self.abbreviation
+    }
+
+    fn limited(&self) -> bool {
+        self.limited
+    }
+
+    fn estimate(&self) -> &Vec<EtdEstimate> {
+        &self.estimate
+    }
+
+    #[graphql(description = "Information about the destination station")]
+    async fn destination_station(&self, context: &Context) -> FieldResult<stninfo::Station> {
+        let station = context
+            .loaders
+            .station_info(self.abbreviation.clone())
+            .await?;
+        Ok((*station).clone())
+    }
+
+    #[graphql(description = "Parking, bike and accessibility details of the destination station")]
+    async fn destination_access(&self, context: &Context) -> FieldResult<stnaccess::Station> {
+        let station = context
+            .loaders
+            .station_access(self.abbreviation.clone())
+            .await?;
+        Ok((*station).clone())
+    }
+}
+
+// Lines come as e.g. "ROUTE 7"
+fn route_number(line: &str) -> FieldResult<u8> {
+    line.trim_start_matches("ROUTE")
+        .trim()
+        .parse()
+        .map_err(|_| FieldError::from(format!("`{}` is not a route", line)))
+}
+
+#[juniper::object(Context = Context)]
+impl Leg {
+    fn order(&self) -> i32 {
+        self.order
+    }
+
+    fn origin(&self) -> &Station {
+        &self.origin
+    }
+
+    fn destination(&self) -> &Station {
+        &self.destination
+    }
+
+    fn orig_time_min(&self) -> &Time {
+        &self.orig_time_min
+    }
+
+    fn orig_time_date(&self) -> &Date {
+        &self.orig_time_date
+    }
+
+    fn dest_time_min(&self) -> &Time {
+        &self.dest_time_min
+    }
+
+    fn dest_time_date(&self) -> &Date {
+        &self.dest_time_date
+    }
+
+    fn line(&self) -> &str {
+        &self.line
+    }
+
+    fn bikeflag(&self) -> bool {
+        self.bikeflag
+    }
+
+    // Does not always exactly match an acutal station name (e.g. "Warm Springs" instead of "Warm
+    // Springs/South Fremont")
+    fn train_head_station(&self) -> &str {
+        &self.train_head_station
+    }
+
+    fn load(&self) -> i32 {
+        self.load
+    }
+
+    #[graphql(description = "The route this leg rides, as currently scheduled")]
+    async fn route(&self, context: &Context) -> FieldResult<Route> {
+        let route = context
+            .loaders
+            .route_info(route_number(&self.line)?)
+            .await?;
+        Ok((*route).clone())
+    }
+}
+
+#[test]
+fn route_numbers_from_lines() {
+    assert_eq!(route_number("ROUTE 7").unwrap(), 7);
+    assert!(route_number("ROUTE").is_err());
+}
@@ -0,0 +1,183 @@
+use super::Context;
+use crate::{
+    client::{
+        apis::{
+            advisories::{
+                bsa::{self, BsaResponse},
+                count::{self, Count},
+                elev::{self, ElevResponse},
+            },
+            real_time_estimates::etd::{self, EtdOptions, EtdResponse},
+            route_information::{
+                routeinfo::{self, RouteInfoOptions, RouteInfoResponse},
+                routes::{self, RoutesOptions, RoutesResponse},
+            },
+            schedule_information::{
+                arrive::{self, ArriveOptions, ArriveResponse, ArriveTripsOptions},
+                depart::{self, DepartOptions, DepartResponse},
+            },
+            station_information::{stnaccess, stninfo, stns},
+            version_information::version::{self, Version},
+        },
+        constants::{direction::Direction, station::Station},
+    },
+    server::options,
+};
+use chrono::{NaiveDate, NaiveTime};
+use juniper::{FieldError, FieldResult};
+use std::convert::TryFrom;
+
+pub fn to_u8(name: &str, value: i32) -> FieldResult<u8> {
+    u8::try_from(value).map_err(|_| FieldError::from(format!("`{}` is out of range", name)))
+}
+
+fn option_to_u8(name: &str, value: Option<i32>) -> FieldResult<Option<u8>> {
+    value.map(|value| to_u8(name, value)).transpose()
+}
+
+pub fn etd_options(
+    station: Option<Station>,
+    direction: Option<Direction>,
+    platform: Option<i32>,
+) -> FieldResult<EtdOptions> {
+    let platform = option_to_u8("platform", platform)?;
+    Ok(options::etd_options(station, direction, platform)?)
+}
+
+pub fn trips_options(
+    before: Option<i32>,
+    after: Option<i32>,
+) -> FieldResult<Option<ArriveTripsOptions>> {
+    let before = option_to_u8("before", before)?;
+    let after = option_to_u8("after", after)?;
+    Ok(options::trips_options(before, after)?)
+}
+
+pub fn time_option(time: Option<String>) -> FieldResult<Option<NaiveTime>> {
+    Ok(options::time_option(time)?)
+}
+
+pub fn routes_options(
+    schedule: Option<i32>,
+    date: Option<NaiveDate>,
+) -> FieldResult<Option<RoutesOptions>> {
+    let schedule = option_to_u8("schedule", schedule)?;
+    Ok(options::routes_options(schedule, date)?)
+}
+
+pub fn route_info_options(
+    schedule: Option<i32>,
+    date: Option<NaiveDate>,
+) -> FieldResult<Option<RouteInfoOptions>> {
+    let schedule = option_to_u8("schedule", schedule)?;
+    Ok(options::route_info_options(schedule, date)?)
+}
+
+pub struct Query;
+
+#[juniper::object(Context = Context)]
+impl Query {
+    async fn stations(context: &Context) -> FieldResult<stns::StationsResponse> {
+        Ok(stns::call(context.key()).await?)
+    }
+
+    async fn station_info(
+        context: &Context,
+        station: Station,
+    ) -> FieldResult<stninfo::StationsResponse> {
+        Ok(stninfo::call(station, context.key()).await?)
+    }
+
+    async fn station_access(
+        context: &Context,
+        station: Station,
+    ) -> FieldResult<stnaccess::StationsResponse> {
+        Ok(stnaccess::call(station, context.key()).await?)
+    }
+
+    async fn routes(
+        context: &Context,
+        schedule: Option<i32>,
+        date: Option<NaiveDate>,
+    ) -> FieldResult<RoutesResponse> {
+        let options = routes_options(schedule, date)?;
+        Ok(routes::call(&options, context.key()).await?)
+    }
+
+    async fn route_info(
+        context: &Context,
+        route: i32,
+        schedule: Option<i32>,
+        date: Option<NaiveDate>,
+    ) -> FieldResult<RouteInfoResponse> {
+        let options = route_info_options(schedule, date)?;
+        Ok(routeinfo::call(to_u8("route", route)?, &options, context.key()).await?)
+    }
+
+    #[graphql(description = "Real-time departures, from every station when `station` is left out")]
+    async fn etd(
+        context: &Context,
+        station: Option<Station>,
+        direction: Option<Direction>,
+        platform: Option<i32>,
+    ) -> FieldResult<EtdResponse> {
+        let options = etd_options(station, direction, platform)?;
+        Ok(etd::call(&options, context.key()).await?)
+    }
+
+    #[graphql(description = "Trips arriving at `dest` by `time` (\"HH:MM\", defaults to now)")]
+    async fn arrive(
+        context: &Context,
+        orig: Station,
+        dest: Station,
+        time: Option<String>,
+        date: Option<NaiveDate>,
+        before: Option<i32>,
+        after: Option<i32>,
+    ) -> FieldResult<ArriveResponse> {
+        let options = ArriveOptions {
+            orig,
+            dest,
+            time: time_option(time)?,
+            date,
+            trips: trips_options(before, after)?,
+        };
+        Ok(arrive::call(&options, context.key()).await?)
+    }
+
+    #[graphql(description = "Trips leaving `orig` at `time` (\"HH:MM\", defaults to now)")]
+    async fn depart(
+        context: &Context,
+        orig: Station,
+        dest: Station,
+        time: Option<String>,
+        date: Option<NaiveDate>,
+        before: Option<i32>,
+        after: Option<i32>,
+    ) -> FieldResult<DepartResponse> {
+        let options = DepartOptions {
+            orig,
+            dest,
+            time: time_option(time)?,
+            date,
+            trips: trips_options(before, after)?,
+        };
+        Ok(depart::call(&options, context.key()).await?)
+    }
+
+    async fn advisories(context: &Context) -> FieldResult<BsaResponse> {
+        Ok(bsa::call(context.key()).await?)
+    }
+
+    async fn elevator_status(context: &Context) -> FieldResult<ElevResponse> {
+        Ok(elev::call(context.key()).await?)
+    }
+
+    async fn train_count(context: &Context) -> FieldResult<Count> {
+        Ok(count::call(context.key()).await?)
+    }
+
+    async fn version(context: &Context) -> FieldResult<Version> {
+        Ok(version::call(context.key()).await?)
+    }
+}
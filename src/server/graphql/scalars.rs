@@ -0,0 +1,190 @@
+use crate::client::{
+    apis::{
+        advisories::{bsa::r#type::BsaType, elev::r#type::ElevType},
+        real_time_estimates::etd::minutes::EtdEstimateMinutes,
+    },
+    constants::{
+        color::Color,
+        datetime::{Date, DateTime, Time, CHRONO_DATE_FORMAT},
+        direction::Direction,
+        fare_type::FareType,
+        station::Station,
+    },
+};
+use chrono::NaiveDate;
+use juniper::{graphql_scalar, ParseScalarResult, ParseScalarValue, Value};
+use std::convert::TryFrom;
+
+// Every scalar is exposed as the same string it serializes to, so GraphQL and JSON responses
+// agree
+
+graphql_scalar!(Station as "StationCode" where Scalar = <S> {
+    description: "A BART station abbreviation, e.g. \"mont\" (full names are accepted as input)"
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_abbr().to_owned())
+    }
+
+    from_input_value(v: &InputValue) -> Option<Station> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| Station::try_from(s.to_owned()).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(Direction where Scalar = <S> {
+    description: "A direction of travel, either \"n\" or \"s\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_code().to_owned())
+    }
+
+    from_input_value(v: &InputValue) -> Option<Direction> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| Direction::try_from(s.to_owned()).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(Color where Scalar = <S> {
+    description: "A route color as a hex code, e.g. \"#ffff33\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_code().to_owned())
+    }
+
+    from_input_value(v: &InputValue) -> Option<Color> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| Color::try_from(s.to_owned()).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(FareType where Scalar = <S> {
+    description: "A fare type, e.g. \"clipper\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_code().to_owned())
+    }
+
+    from_input_value(v: &InputValue) -> Option<FareType> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| FareType::try_from(s.to_owned()).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(BsaType where Scalar = <S> {
+    description: "A service advisory type, e.g. \"DELAY\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_code().to_owned())
+    }
+
+    from_input_value(v: &InputValue) -> Option<BsaType> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| BsaType::from_code(s).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(ElevType where Scalar = <S> {
+    description: "An elevator advisory type, e.g. \"ELEVATOR\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_code().to_owned())
+    }
+
+    from_input_value(v: &InputValue) -> Option<ElevType> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| ElevType::from_code(s).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(EtdEstimateMinutes where Scalar = <S> {
+    description: "Minutes until departure, or \"Leaving\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_string())
+    }
+
+    from_input_value(v: &InputValue) -> Option<EtdEstimateMinutes> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| EtdEstimateMinutes::from_string(s).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(Date where Scalar = <S> {
+    description: "A date formatted as \"MM/DD/YYYY\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.0.format(CHRONO_DATE_FORMAT).to_string())
+    }
+
+    from_input_value(v: &InputValue) -> Option<Date> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| NaiveDate::parse_from_str(s, CHRONO_DATE_FORMAT).ok())
+            .map(Date)
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(Time where Scalar = <S> {
+    description: "A time formatted as \"HH:MM:SS AM\", followed by the time zone when BART sends one"
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_string())
+    }
+
+    from_input_value(v: &InputValue) -> Option<Time> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| Time::from_short_string_without_tz(s).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
+
+graphql_scalar!(DateTime where Scalar = <S> {
+    description: "A date and time formatted as \"Mon Jan 01 2019 12:00 AM PST\""
+
+    resolve(&self) -> Value {
+        Value::scalar(self.to_string())
+    }
+
+    from_input_value(v: &InputValue) -> Option<DateTime> {
+        v.as_scalar_value::<String>()
+            .and_then(|s| DateTime::from_string(s).ok())
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+});
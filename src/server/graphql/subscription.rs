@@ -0,0 +1,48 @@
+use super::{query::etd_options, Context};
+use crate::{
+    client::{
+        apis::{
+            advisories::{bsa::BsaResponse, elev::ElevResponse},
+            real_time_estimates::etd::EtdStation,
+        },
+        constants::{direction::Direction, station::Station},
+    },
+    server::poller::departures_station,
+};
+use futures::{Stream, StreamExt};
+use juniper::FieldResult;
+use std::pin::Pin;
+
+pub type EtdStationStream = Pin<Box<dyn Stream<Item = EtdStation> + Send>>;
+pub type BsaResponseStream = Pin<Box<dyn Stream<Item = BsaResponse> + Send>>;
+pub type ElevResponseStream = Pin<Box<dyn Stream<Item = ElevResponse> + Send>>;
+
+pub struct Subscription;
+
+#[juniper::subscription(Context = Context)]
+impl Subscription {
+    #[graphql(description = "Departures from `station`, pushed whenever the estimates change")]
+    async fn departures(
+        context: &Context,
+        station: Station,
+        direction: Option<Direction>,
+        platform: Option<i32>,
+    ) -> FieldResult<EtdStationStream> {
+        let options = etd_options(Some(station.clone()), direction, platform)?;
+        let stream = context
+            .pollers
+            .etd(options)
+            .map(move |response| departures_station(&station, response));
+        Ok(Box::pin(stream))
+    }
+
+    #[graphql(description = "Service advisories, pushed whenever one appears, changes or clears")]
+    async fn advisories(context: &Context) -> FieldResult<BsaResponseStream> {
+        Ok(Box::pin(context.pollers.bsa()))
+    }
+
+    #[graphql(description = "Elevator outages, pushed whenever one appears, changes or clears")]
+    async fn elevator_status(context: &Context) -> FieldResult<ElevResponseStream> {
+        Ok(Box::pin(context.pollers.elev()))
+    }
+}
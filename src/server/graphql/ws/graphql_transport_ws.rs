@@ -0,0 +1,78 @@
+// Messages of the `graphql-transport-ws` subprotocol used by `graphql-ws`
+use super::protocol::{Incoming, Outgoing};
+use juniper::http::GraphQLRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PROTOCOL: &str = "graphql-transport-ws";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    ConnectionInit {
+        #[serde(default)]
+        payload: Option<Value>,
+    },
+    Ping {
+        #[serde(default)]
+        payload: Option<Value>,
+    },
+    Pong {
+        #[serde(default)]
+        payload: Option<Value>,
+    },
+    Subscribe {
+        id: String,
+        payload: GraphQLRequest,
+    },
+    Complete {
+        id: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    ConnectionAck,
+    Ping,
+    Pong {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        payload: Option<Value>,
+    },
+    Next {
+        id: String,
+        payload: Value,
+    },
+    Error {
+        id: String,
+        payload: Value,
+    },
+    Complete {
+        id: String,
+    },
+}
+
+impl From<ClientMessage> for Incoming {
+    fn from(message: ClientMessage) -> Self {
+        match message {
+            ClientMessage::ConnectionInit { payload } => Incoming::Init(payload),
+            ClientMessage::Ping { payload } => Incoming::Ping(payload),
+            ClientMessage::Pong { .. } => Incoming::Pong,
+            ClientMessage::Subscribe { id, payload } => Incoming::Subscribe(id, payload),
+            ClientMessage::Complete { id } => Incoming::Complete(id),
+        }
+    }
+}
+
+// Connection errors are reported by closing the socket instead of with a message
+pub fn from_outgoing(outgoing: Outgoing) -> Option<ServerMessage> {
+    match outgoing {
+        Outgoing::Ack => Some(ServerMessage::ConnectionAck),
+        Outgoing::ConnectionError(_) => None,
+        Outgoing::Next(id, payload) => Some(ServerMessage::Next { id, payload }),
+        Outgoing::Error(id, payload) => Some(ServerMessage::Error { id, payload }),
+        Outgoing::Complete(id) => Some(ServerMessage::Complete { id }),
+        Outgoing::KeepAlive => Some(ServerMessage::Ping),
+        Outgoing::Pong(payload) => Some(ServerMessage::Pong { payload }),
+    }
+}
@@ -0,0 +1,70 @@
+// Messages of the legacy `graphql-ws` subprotocol used by Apollo's `subscriptions-transport-ws`
+use super::protocol::{Incoming, Outgoing};
+use juniper::http::GraphQLRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PROTOCOL: &str = "graphql-ws";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    ConnectionInit {
+        #[serde(default)]
+        payload: Option<Value>,
+    },
+    Start {
+        id: String,
+        payload: GraphQLRequest,
+    },
+    Stop {
+        id: String,
+    },
+    ConnectionTerminate,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    ConnectionAck,
+    ConnectionError {
+        payload: Value,
+    },
+    Data {
+        id: String,
+        payload: Value,
+    },
+    Error {
+        id: String,
+        payload: Value,
+    },
+    Complete {
+        id: String,
+    },
+    #[serde(rename = "ka")]
+    KeepAlive,
+}
+
+impl From<ClientMessage> for Incoming {
+    fn from(message: ClientMessage) -> Self {
+        match message {
+            ClientMessage::ConnectionInit { payload } => Incoming::Init(payload),
+            ClientMessage::Start { id, payload } => Incoming::Subscribe(id, payload),
+            ClientMessage::Stop { id } => Incoming::Complete(id),
+            ClientMessage::ConnectionTerminate => Incoming::Terminate,
+        }
+    }
+}
+
+// There is no ping/pong in this protocol, keep-alives are one way
+pub fn from_outgoing(outgoing: Outgoing) -> Option<ServerMessage> {
+    match outgoing {
+        Outgoing::Ack => Some(ServerMessage::ConnectionAck),
+        Outgoing::ConnectionError(payload) => Some(ServerMessage::ConnectionError { payload }),
+        Outgoing::Next(id, payload) => Some(ServerMessage::Data { id, payload }),
+        Outgoing::Error(id, payload) => Some(ServerMessage::Error { id, payload }),
+        Outgoing::Complete(id) => Some(ServerMessage::Complete { id }),
+        Outgoing::KeepAlive => Some(ServerMessage::KeepAlive),
+        Outgoing::Pong(_) => None,
+    }
+}
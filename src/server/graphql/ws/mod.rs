@@ -0,0 +1,341 @@
+pub mod graphql_transport_ws;
+pub mod graphql_ws;
+pub mod protocol;
+
+use self::protocol::{
+    Incoming, Outgoing, Protocol, CLOSE_BAD_REQUEST, CLOSE_FORBIDDEN, CLOSE_INIT_TIMEOUT,
+    CLOSE_SUBSCRIBER_EXISTS, CLOSE_TOO_MANY_INITS, CLOSE_UNAUTHORIZED,
+};
+use super::{complexity::Limits, Context, Coordinator};
+use crate::server::{
+    auth::{Client, Clients, API_KEY_PAYLOAD_FIELD},
+    config::Api,
+    outbox::{self, Overflow, Pushed},
+    shutdown::Shutdown,
+};
+use futures::{
+    future::{abortable, AbortHandle, FutureExt},
+    stream::{self, StreamExt},
+};
+use juniper::http::GraphQLRequest;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::timer::{delay_for, Interval};
+use warp::ws::{Message, WebSocket};
+
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+pub const CLOSE_SLOW_CONSUMER: u16 = 1008;
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+pub const CONNECTION_INIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[cfg(feature = "metrics")]
+const TRANSPORT: &str = "websocket";
+
+type Operations = Arc<Mutex<HashMap<String, AbortHandle>>>;
+
+#[derive(Clone)]
+struct Connection {
+    protocol: Protocol,
+    outbox: outbox::Sender<Message>,
+}
+
+impl Connection {
+    // Every subscription yields whole snapshots, so only the latest `next` of an operation that
+    // the client hasn't read yet is worth sending
+    fn send(&self, outgoing: Outgoing) {
+        let key = match &outgoing {
+            Outgoing::Next(id, _) => Some(id.clone()),
+            _ => None,
+        };
+        let text = match self.protocol.encode(outgoing) {
+            Some(text) => text,
+            None => return,
+        };
+        let pushed = match key {
+            Some(id) => self.outbox.push_latest(id, Message::text(text)),
+            None => self.outbox.push(Message::text(text)),
+        };
+        match pushed {
+            Ok(Pushed::Replaced) => {
+                #[cfg(feature = "metrics")]
+                crate::server::metrics::observe_dropped_update(TRANSPORT);
+            }
+            Ok(_) => {}
+            Err(Overflow) => {
+                #[cfg(feature = "metrics")]
+                crate::server::metrics::observe_slow_consumer(TRANSPORT);
+                self.close(CLOSE_SLOW_CONSUMER, "Client is not keeping up");
+            }
+        }
+    }
+
+    fn close(&self, code: u16, reason: &'static str) {
+        self.outbox.close_with(Message::close_with(code, reason));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.outbox.is_closed()
+    }
+}
+
+async fn run_operation(
+    id: String,
+    request: GraphQLRequest,
+    coordinator: Arc<Coordinator>,
+    context: Context,
+    connection: Connection,
+    operations: Operations,
+) {
+    match coordinator.subscribe(&request, &context).await {
+        Ok(mut stream) => {
+            while let Some(response) = stream.next().await {
+                if connection.is_closed() {
+                    break;
+                }
+                connection.send(Outgoing::Next(
+                    id.clone(),
+                    serde_json::to_value(&response).unwrap_or_default(),
+                ));
+            }
+            connection.send(Outgoing::Complete(id.clone()));
+        }
+        Err(error) => connection.send(Outgoing::Error(
+            id.clone(),
+            serde_json::to_value(&[error]).unwrap_or_default(),
+        )),
+    }
+    operations.lock().unwrap().remove(&id);
+}
+
+async fn keep_alive(connection: Connection) {
+    let mut interval = Interval::new_interval(KEEP_ALIVE_INTERVAL);
+    while interval.next().await.is_some() {
+        connection.send(Outgoing::KeepAlive);
+    }
+}
+
+// `graphql-transport-ws` clients that never initialize are disconnected
+async fn init_timeout(connection: Connection, acknowledged: Arc<AtomicBool>) {
+    delay_for(CONNECTION_INIT_TIMEOUT).await;
+    if !acknowledged.load(Ordering::SeqCst) {
+        connection.close(CLOSE_INIT_TIMEOUT, "Connection initialisation timeout");
+    }
+}
+
+// Everything a connection shares with the rest of the server
+#[derive(Clone)]
+pub struct Services {
+    pub coordinator: Arc<Coordinator>,
+    pub limits: Arc<Limits>,
+    pub clients: Arc<Clients>,
+    pub shutdown: Shutdown,
+}
+
+// `api_key` is the one sent along with the upgrade request, if any
+pub async fn serve(
+    websocket: WebSocket,
+    protocol: Protocol,
+    api_key: Option<String>,
+    context: Context,
+    services: Services,
+) {
+    let (sink, stream) = websocket.split();
+    let (outbox, messages) = outbox::channel(outbox::DEFAULT_CAPACITY, outbox::DEFAULT_MAX_DROPPED);
+    // Shutdown waits for the writer, so that the last `complete` and close frames go out
+    let live = services.shutdown.connection();
+    tokio::spawn(
+        messages
+            .map(Ok::<_, warp::Error>)
+            .forward(sink)
+            .map(move |result| {
+                if let Err(error) = result {
+                    log::debug!("websocket error: {:?}", error);
+                }
+                drop(live);
+            }),
+    );
+    let connection = Connection { protocol, outbox };
+    #[cfg(feature = "metrics")]
+    crate::server::metrics::WEBSOCKET_CONNECTIONS.inc();
+
+    let acknowledged = Arc::new(AtomicBool::new(false));
+    let mut background: Vec<AbortHandle> = Vec::new();
+    if protocol.is_strict() {
+        let (timeout, handle) = abortable(init_timeout(connection.clone(), acknowledged.clone()));
+        tokio::spawn(timeout.map(|_| ()));
+        background.push(handle);
+    }
+
+    // The server shutting down is just one more thing that can happen to the connection
+    let shutdown = stream::once(services.shutdown.clone().wait()).map(|_| None);
+    let mut events = stream::select(stream.map(Some), shutdown.boxed());
+
+    let operations: Operations = Arc::new(Mutex::new(HashMap::new()));
+    let mut client: Option<Arc<Client>> = None;
+    while let Some(event) = events.next().await {
+        let message = match event {
+            Some(message) => message,
+            None => {
+                let running: Vec<(String, AbortHandle)> =
+                    operations.lock().unwrap().drain().collect();
+                for (id, handle) in running {
+                    handle.abort();
+                    connection.send(Outgoing::Complete(id));
+                }
+                connection.close(CLOSE_GOING_AWAY, "Server is shutting down");
+                break;
+            }
+        };
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => {
+                log::debug!("websocket error: {:?}", error);
+                break;
+            }
+        };
+        if message.is_close() {
+            break;
+        }
+        let text = match message.to_str() {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+
+        let incoming = match protocol.decode(text) {
+            Ok(incoming) => incoming,
+            Err(error) => {
+                if protocol.is_strict() {
+                    connection.close(CLOSE_BAD_REQUEST, "Invalid message received");
+                    break;
+                }
+                connection.send(Outgoing::ConnectionError(
+                    serde_json::json!({ "message": error.to_string() }),
+                ));
+                continue;
+            }
+        };
+
+        match incoming {
+            Incoming::Init(payload) => {
+                // The connection is already set up, keep-alive included
+                if acknowledged.load(Ordering::SeqCst) {
+                    if protocol.is_strict() {
+                        connection.close(CLOSE_TOO_MANY_INITS, "Too many initialisation requests");
+                        break;
+                    }
+                    continue;
+                }
+
+                // A key in the payload wins over one sent when upgrading
+                let key = payload
+                    .as_ref()
+                    .and_then(|payload| payload.get(API_KEY_PAYLOAD_FIELD))
+                    .and_then(Value::as_str)
+                    .or_else(|| api_key.as_ref().map(String::as_str));
+                match services.clients.authenticate(key) {
+                    Ok(authenticated) => client = authenticated,
+                    Err(denied) => {
+                        if !protocol.is_strict() {
+                            connection.send(Outgoing::ConnectionError(
+                                serde_json::json!({ "message": denied.message() }),
+                            ));
+                        }
+                        connection.close(CLOSE_FORBIDDEN, denied.message());
+                        break;
+                    }
+                }
+
+                acknowledged.store(true, Ordering::SeqCst);
+                connection.send(Outgoing::Ack);
+                let (keep_alive, handle) = abortable(keep_alive(connection.clone()));
+                tokio::spawn(keep_alive.map(|_| ()));
+                background.push(handle);
+            }
+            Incoming::Subscribe(id, request) => {
+                if !acknowledged.load(Ordering::SeqCst) {
+                    if protocol.is_strict() {
+                        connection.close(CLOSE_UNAUTHORIZED, "Unauthorized");
+                        break;
+                    }
+                    connection.send(Outgoing::ConnectionError(
+                        serde_json::json!({ "message": "Connection has not been initialized" }),
+                    ));
+                    continue;
+                }
+
+                if let Some(client) = &client {
+                    if let Err(denied) = client.admit(Api::GraphQL) {
+                        connection.send(Outgoing::Error(
+                            id,
+                            serde_json::json!([{ "message": denied.message() }]),
+                        ));
+                        continue;
+                    }
+                }
+
+                if let Err(error) = services.limits.check_request(&request) {
+                    connection.send(Outgoing::Error(
+                        id,
+                        serde_json::json!([{ "message": error.to_string() }]),
+                    ));
+                    continue;
+                }
+
+                // The guard is kept out of scope of any await point
+                let duplicate = {
+                    let mut running = operations.lock().unwrap();
+                    if running.contains_key(&id) && protocol.is_strict() {
+                        true
+                    } else {
+                        let (operation, handle) = abortable(run_operation(
+                            id.clone(),
+                            request,
+                            services.coordinator.clone(),
+                            context.for_request(),
+                            connection.clone(),
+                            operations.clone(),
+                        ));
+                        if let Some(previous) = running.insert(id, handle) {
+                            previous.abort();
+                        }
+                        tokio::spawn(operation.map(|_| ()));
+                        false
+                    }
+                };
+                if duplicate {
+                    connection.close(CLOSE_SUBSCRIBER_EXISTS, "Subscriber already exists");
+                    break;
+                }
+            }
+            Incoming::Complete(id) => {
+                let handle = operations.lock().unwrap().remove(&id);
+                if let Some(handle) = handle {
+                    handle.abort();
+                    if protocol.completes_stopped_operations() {
+                        connection.send(Outgoing::Complete(id));
+                    }
+                }
+            }
+            Incoming::Ping(payload) => connection.send(Outgoing::Pong(payload)),
+            Incoming::Pong => {}
+            Incoming::Terminate => break,
+        }
+    }
+
+    for handle in background {
+        handle.abort();
+    }
+    for (_, handle) in operations.lock().unwrap().drain() {
+        handle.abort();
+    }
+    connection.outbox.close();
+    #[cfg(feature = "metrics")]
+    crate::server::metrics::WEBSOCKET_CONNECTIONS.dec();
+}
@@ -0,0 +1,122 @@
+use super::{graphql_transport_ws, graphql_ws};
+use anyhow::Result;
+use juniper::http::GraphQLRequest;
+use serde_json::Value;
+
+pub const CLOSE_BAD_REQUEST: u16 = 4400;
+pub const CLOSE_UNAUTHORIZED: u16 = 4401;
+pub const CLOSE_FORBIDDEN: u16 = 4403;
+pub const CLOSE_INIT_TIMEOUT: u16 = 4408;
+pub const CLOSE_SUBSCRIBER_EXISTS: u16 = 4409;
+pub const CLOSE_TOO_MANY_INITS: u16 = 4429;
+
+// What either subprotocol can ask of the server
+#[derive(Debug)]
+pub enum Incoming {
+    Init(Option<Value>),
+    Subscribe(String, GraphQLRequest),
+    Complete(String),
+    Ping(Option<Value>),
+    Pong,
+    Terminate,
+}
+
+// What the server can tell a client of either subprotocol
+#[derive(Debug)]
+pub enum Outgoing {
+    Ack,
+    ConnectionError(Value),
+    Next(String, Value),
+    Error(String, Value),
+    Complete(String),
+    KeepAlive,
+    Pong(Option<Value>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    GraphQLWs,
+    GraphQLTransportWs,
+}
+
+impl Protocol {
+    pub fn from_name<T: AsRef<str>>(name: T) -> Option<Protocol> {
+        match name.as_ref().trim() {
+            graphql_ws::PROTOCOL => Some(Protocol::GraphQLWs),
+            graphql_transport_ws::PROTOCOL => Some(Protocol::GraphQLTransportWs),
+            _ => None,
+        }
+    }
+
+    // Picks the first supported protocol out of a `Sec-WebSocket-Protocol` header. Clients that
+    // don't send the header at all are assumed to be legacy Apollo clients.
+    pub fn negotiate(header: Option<&str>) -> Option<Protocol> {
+        match header {
+            Some(header) => header.split(',').filter_map(Protocol::from_name).next(),
+            None => Some(Protocol::GraphQLWs),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::GraphQLWs => graphql_ws::PROTOCOL,
+            Protocol::GraphQLTransportWs => graphql_transport_ws::PROTOCOL,
+        }
+    }
+
+    // `graphql-ws` clients expect the server to confirm a stop, `graphql-transport-ws` clients
+    // must not get a `complete` for an operation they completed themselves
+    pub fn completes_stopped_operations(&self) -> bool {
+        match self {
+            Protocol::GraphQLWs => true,
+            Protocol::GraphQLTransportWs => false,
+        }
+    }
+
+    // `graphql-transport-ws` is strict about the handshake and closes the socket on violations
+    pub fn is_strict(&self) -> bool {
+        match self {
+            Protocol::GraphQLWs => false,
+            Protocol::GraphQLTransportWs => true,
+        }
+    }
+
+    pub fn decode<T: AsRef<str>>(&self, text: T) -> Result<Incoming> {
+        Ok(match self {
+            Protocol::GraphQLWs => {
+                serde_json::from_str::<graphql_ws::ClientMessage>(text.as_ref())?.into()
+            }
+            Protocol::GraphQLTransportWs => {
+                serde_json::from_str::<graphql_transport_ws::ClientMessage>(text.as_ref())?.into()
+            }
+        })
+    }
+
+    pub fn encode(&self, outgoing: Outgoing) -> Option<String> {
+        let text = match self {
+            Protocol::GraphQLWs => {
+                graphql_ws::from_outgoing(outgoing).map(|message| serde_json::to_string(&message))
+            }
+            Protocol::GraphQLTransportWs => graphql_transport_ws::from_outgoing(outgoing)
+                .map(|message| serde_json::to_string(&message)),
+        };
+        match text {
+            Some(Ok(text)) => Some(text),
+            Some(Err(error)) => {
+                log::error!("Could not serialize websocket message: {}", error);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+#[test]
+fn negotiate_protocol() {
+    assert_eq!(Protocol::negotiate(None), Some(Protocol::GraphQLWs));
+    assert_eq!(
+        Protocol::negotiate(Some("graphql-transport-ws, graphql-ws")),
+        Some(Protocol::GraphQLTransportWs)
+    );
+    assert_eq!(Protocol::negotiate(Some("mqtt")), None);
+}
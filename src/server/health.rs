@@ -0,0 +1,178 @@
+use crate::{
+    client::{
+        apis::version_information::version,
+        cache,
+        constants::endpoint::Endpoint,
+        health::{self, EndpointHealth, Health},
+        keys::{self, KeyHealth},
+    },
+    server::{poller::Registry, Routes},
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::timer::delay_for;
+use warp::{http::StatusCode, Filter, Reply};
+
+pub const VERSION_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+// Versions are cached for an hour, so a success can be that old without anything being wrong
+pub const VERSION_MAX_AGE: Duration = Duration::from_secs(2 * 60 * 60);
+// Pollers get a few chances before the instance is taken out of rotation
+pub const ETD_MAX_MISSED_POLLS: u32 = 4;
+
+#[derive(Debug, Serialize)]
+pub struct EndpointStatus {
+    pub age_seconds: Option<f64>,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+impl From<&EndpointHealth> for EndpointStatus {
+    fn from(health: &EndpointHealth) -> Self {
+        EndpointStatus {
+            age_seconds: health
+                .age()
+                .map(|age| age.as_secs() as f64 + f64::from(age.subsec_millis()) / 1e3),
+            consecutive_failures: health.consecutive_failures,
+            last_error: health.last_error.clone(),
+        }
+    }
+}
+
+// Keys are masked, since `/status` is open to anyone who can reach the server
+#[derive(Debug, Serialize)]
+pub struct KeyStatus {
+    pub key: String,
+    pub in_rotation: bool,
+    pub back_in_seconds: Option<u64>,
+    pub requests: u64,
+    pub rejections: u64,
+    pub throttles: u64,
+    pub last_error: Option<String>,
+}
+
+impl KeyStatus {
+    fn new(key: &str, health: &KeyHealth, now: Instant) -> KeyStatus {
+        KeyStatus {
+            key: keys::mask(key),
+            in_rotation: health.in_rotation(now),
+            back_in_seconds: health
+                .out_until
+                .filter(|until| *until > now)
+                .map(|until| until.duration_since(now).as_secs()),
+            requests: health.requests,
+            rejections: health.rejections,
+            throttles: health.throttles,
+            last_error: health.last_error.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub ready: bool,
+    pub reasons: Vec<String>,
+    pub sched_num: Option<i32>,
+    pub pollers: Vec<String>,
+    pub endpoints: BTreeMap<&'static str, EndpointStatus>,
+    pub keys: Vec<KeyStatus>,
+}
+
+// Ready once BART has answered a version call, and as long as it keeps answering the pollers
+pub fn readiness(health: &Health, pollers: &Registry) -> Readiness {
+    let mut reasons = vec![];
+
+    if !health.get(Endpoint::Version).is_fresh(VERSION_MAX_AGE) {
+        reasons.push(String::from("BART has not answered a recent version call"));
+    }
+
+    let etd_max_age = pollers.intervals().etd * ETD_MAX_MISSED_POLLS;
+    let polling_etd = !pollers.etd_subscribers().is_empty();
+    if polling_etd && !health.get(Endpoint::Etd).is_fresh(etd_max_age) {
+        reasons.push(String::from(
+            "Departure pollers have not succeeded recently",
+        ));
+    }
+
+    Readiness {
+        ready: reasons.is_empty(),
+        reasons,
+    }
+}
+
+pub fn status(pollers: &Registry) -> Status {
+    let Readiness { ready, reasons } = readiness(health::global(), pollers);
+    let endpoints = health::global()
+        .snapshot()
+        .iter()
+        .map(|(endpoint, health)| (endpoint.to_code(), EndpointStatus::from(health)))
+        .collect();
+    let now = Instant::now();
+    let keys = keys::global()
+        .snapshot()
+        .iter()
+        .map(|(key, health)| KeyStatus::new(key, health, now))
+        .collect();
+    Status {
+        ready,
+        reasons,
+        sched_num: cache::global().sched_num(),
+        pollers: pollers.active(),
+        endpoints,
+        keys,
+    }
+}
+
+// Keeps the version endpoint, and with it readiness, up to date
+pub async fn probe_version(key: Option<String>) {
+    loop {
+        if let Err(error) = version::call(key.clone()).await {
+            log::warn!("Version probe failed: {:#}", error);
+        }
+        delay_for(VERSION_PROBE_INTERVAL).await;
+    }
+}
+
+pub fn routes(pollers: Arc<Registry>) -> Routes {
+    let healthz = warp::path("healthz")
+        .and(warp::path::end())
+        .map(|| Box::new("ok") as Box<dyn Reply>);
+
+    let readyz_pollers = pollers.clone();
+    let readyz = warp::path("readyz").and(warp::path::end()).map(move || {
+        let readiness = readiness(health::global(), &readyz_pollers);
+        let status = if readiness.ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        Box::new(warp::reply::with_status(
+            warp::reply::json(&readiness),
+            status,
+        )) as Box<dyn Reply>
+    });
+
+    let status = warp::path("status")
+        .and(warp::path::end())
+        .map(move || Box::new(warp::reply::json(&status(&pollers))) as Box<dyn Reply>);
+
+    warp::get2()
+        .and(healthz.or(readyz).unify().or(status).unify())
+        .boxed()
+}
+
+#[test]
+fn not_ready_without_version() {
+    let readiness = readiness(&Health::new(), &Registry::default());
+    assert!(!readiness.ready);
+    assert_eq!(readiness.reasons.len(), 1);
+}
@@ -0,0 +1,135 @@
+use crate::server::{poller::Registry, Routes};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge_vec, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+    Encoder, GaugeVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use warp::{http::StatusCode, Filter, Reply};
+
+lazy_static! {
+    static ref LAST_POLLS: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+    pub static ref WEBSOCKET_CONNECTIONS: IntGauge = register_int_gauge!(
+        "sfbart_websocket_connections",
+        "Open GraphQL websocket connections"
+    )
+    .unwrap();
+    static ref ACTIVE_SUBSCRIPTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "sfbart_active_subscriptions",
+        "Live departure subscribers, by station",
+        &["station"]
+    )
+    .unwrap();
+    static ref POLLER_LAG: GaugeVec = register_gauge_vec!(
+        "sfbart_poller_lag_seconds",
+        "Seconds since each running poller last heard back from BART",
+        &["poller"]
+    )
+    .unwrap();
+    static ref DROPPED_UPDATES: IntCounterVec = register_int_counter_vec!(
+        "sfbart_subscription_updates_dropped_total",
+        "Subscription updates replaced by a newer one before the client read them, by transport",
+        &["transport"]
+    )
+    .unwrap();
+    static ref CLIENT_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "sfbart_client_requests_total",
+        "Requests by configured clients, by client, API and whether they were let through",
+        &["client", "api", "outcome"]
+    )
+    .unwrap();
+    static ref WEBHOOK_DELIVERIES: IntCounterVec = register_int_counter_vec!(
+        "sfbart_webhook_deliveries_total",
+        "Webhook delivery attempts, by whether they were delivered, retried or dead-lettered",
+        &["outcome"]
+    )
+    .unwrap();
+    static ref SLOW_CONSUMER_DISCONNECTS: IntCounterVec = register_int_counter_vec!(
+        "sfbart_slow_consumer_disconnects_total",
+        "Subscribers disconnected for falling too far behind, by transport",
+        &["transport"]
+    )
+    .unwrap();
+}
+
+pub fn observe_poll(poller: &str) {
+    LAST_POLLS
+        .lock()
+        .unwrap()
+        .insert(String::from(poller), Instant::now());
+}
+
+pub fn observe_dropped_update(transport: &str) {
+    DROPPED_UPDATES.with_label_values(&[transport]).inc();
+}
+
+pub fn observe_slow_consumer(transport: &str) {
+    SLOW_CONSUMER_DISCONNECTS
+        .with_label_values(&[transport])
+        .inc();
+}
+
+pub fn observe_webhook_delivery(outcome: &str) {
+    WEBHOOK_DELIVERIES.with_label_values(&[outcome]).inc();
+}
+
+pub fn observe_client_request(client: &str, api: &str, outcome: &str) {
+    CLIENT_REQUESTS
+        .with_label_values(&[client, api, outcome])
+        .inc();
+}
+
+// Subscription gauges are derived from the registry on every scrape rather than kept up to date
+// by every subscriber
+fn observe_registry(pollers: &Registry) {
+    ACTIVE_SUBSCRIPTIONS.reset();
+    for (options, subscribers) in pollers.etd_subscribers() {
+        let station = options
+            .station()
+            .map(|station| station.to_abbr())
+            .unwrap_or("all");
+        ACTIVE_SUBSCRIPTIONS
+            .with_label_values(&[station])
+            .add(subscribers as i64);
+    }
+
+    let active = pollers.active();
+    let mut last_polls = LAST_POLLS.lock().unwrap();
+    last_polls.retain(|poller, _| active.contains(poller));
+    POLLER_LAG.reset();
+    for (poller, last_poll) in last_polls.iter() {
+        let lag = last_poll.elapsed();
+        POLLER_LAG
+            .with_label_values(&[poller])
+            .set(lag.as_secs() as f64 + f64::from(lag.subsec_nanos()) / 1e9);
+    }
+}
+
+pub fn gather(pollers: &Registry) -> prometheus::Result<Vec<u8>> {
+    observe_registry(pollers);
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(buffer)
+}
+
+pub fn routes(pollers: Arc<Registry>) -> Routes {
+    warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get2())
+        .map(move || match gather(&pollers) {
+            Ok(buffer) => Box::new(warp::reply::with_header(
+                buffer,
+                "content-type",
+                TextEncoder::new().format_type(),
+            )) as Box<dyn Reply>,
+            Err(error) => Box::new(warp::reply::with_status(
+                error.to_string(),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )) as Box<dyn Reply>,
+        })
+        .boxed()
+}
@@ -0,0 +1,111 @@
+use crate::{
+    client::{
+        constants::{endpoint::Endpoint, PUBLIC_KEY},
+        request::{get_body, BartError},
+    },
+    server::Routes,
+};
+use anyhow::{anyhow, Result};
+use url::{form_urlencoded, Url};
+use warp::{Filter, Reply};
+
+pub const BART_API_ROOT: &str = "https://api.bart.gov/api/";
+
+// Which endpoint a BART path and `cmd` refer to. The version endpoint ignores `cmd`.
+pub fn endpoint(file: &str, cmd: Option<&str>) -> Result<Endpoint> {
+    match (file, cmd) {
+        ("version.aspx", _) => Ok(Endpoint::Version),
+        ("etd.aspx", Some("etd")) => Ok(Endpoint::Etd),
+        ("bsa.aspx", Some("bsa")) => Ok(Endpoint::Bsa),
+        ("bsa.aspx", Some("count")) => Ok(Endpoint::Count),
+        ("bsa.aspx", Some("elev")) => Ok(Endpoint::Elev),
+        ("stn.aspx", Some("stns")) => Ok(Endpoint::Stns),
+        ("stn.aspx", Some("stninfo")) => Ok(Endpoint::StnInfo),
+        ("stn.aspx", Some("stnaccess")) => Ok(Endpoint::StnAccess),
+        ("route.aspx", Some("routes")) => Ok(Endpoint::Routes),
+        ("route.aspx", Some("routeinfo")) => Ok(Endpoint::RouteInfo),
+        ("sched.aspx", Some("arrive")) => Ok(Endpoint::Arrive),
+        ("sched.aspx", Some("depart")) => Ok(Endpoint::Depart),
+        (file, Some(cmd)) => Err(anyhow!("`{}` does not support `cmd={}`", file, cmd)),
+        (file, None) => Err(anyhow!("`{}` requires `cmd`", file)),
+    }
+}
+
+// Rewrites a request made against the mirror into the one to make against BART. The client's
+// key is swapped for the server's and JSON is always asked for, since that's all that's cached.
+pub fn upstream_url(file: &str, query: &str, key: Option<&str>) -> Result<(Endpoint, String)> {
+    let pairs: Vec<(String, String)> = form_urlencoded::parse(query.as_bytes())
+        .filter(|(name, _)| name != "key" && name != "json")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    let cmd = pairs
+        .iter()
+        .find(|(name, _)| name == "cmd")
+        .map(|(_, value)| value.as_str());
+    let endpoint = endpoint(file, cmd)?;
+
+    let mut url = Url::parse(BART_API_ROOT)?.join(file)?;
+    url.query_pairs_mut()
+        .extend_pairs(pairs.iter())
+        .append_pair("json", "y")
+        .append_pair("key", key.unwrap_or(PUBLIC_KEY));
+    Ok((endpoint, url.into_string()))
+}
+
+fn json_reply(body: String) -> Box<dyn Reply> {
+    Box::new(warp::reply::with_header(
+        body,
+        "content-type",
+        "application/json",
+    ))
+}
+
+// Legacy clients only know BART's errors, which come with a 200. Those BART sent are passed on
+// as they are, anything else is made to look like one.
+fn error_reply(error: anyhow::Error) -> Box<dyn Reply> {
+    if let Some(error) = error.downcast_ref::<BartError>() {
+        return json_reply(String::from(error.body.as_str()));
+    }
+    let body = serde_json::json!({
+        "root": { "message": { "error": { "text": format!("{:#}", error) } } }
+    });
+    json_reply(body.to_string())
+}
+
+// Answers go through the same cache the pollers keep warm, so a legacy client asking for what a
+// subscription is already watching never reaches BART
+async fn mirror(file: String, query: String, key: Option<String>) -> Box<dyn Reply> {
+    let (endpoint, url) = match upstream_url(&file, &query, key.as_ref().map(String::as_str)) {
+        Ok(upstream) => upstream,
+        Err(error) => return error_reply(error),
+    };
+    match get_body(endpoint, url).await {
+        Ok(body) => json_reply(String::from(body.as_str())),
+        Err(error) => error_reply(error),
+    }
+}
+
+pub fn routes(key: Option<String>) -> Routes {
+    warp::path("api")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get2())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and_then(move |file: String, query: String| {
+            let key = key.clone();
+            async move { Ok::<_, warp::Rejection>(mirror(file, query, key).await) }
+        })
+        .boxed()
+}
+
+#[test]
+fn upstream_url_swaps_key() {
+    let (endpoint, url) =
+        upstream_url("etd.aspx", "cmd=etd&orig=mont&key=CLIENT", Some("SERVER")).unwrap();
+    assert_eq!(endpoint, Endpoint::Etd);
+    assert_eq!(
+        url,
+        "https://api.bart.gov/api/etd.aspx?cmd=etd&orig=mont&json=y&key=SERVER"
+    );
+    assert!(upstream_url("etd.aspx", "cmd=nope", None).is_err());
+}
@@ -0,0 +1,217 @@
+pub mod auth;
+pub mod config;
+#[cfg(feature = "metrics")]
+pub mod exporter;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod health;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod mirror;
+pub mod options;
+pub mod outbox;
+pub mod poller;
+pub mod rest;
+pub mod shutdown;
+pub mod sse;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+
+use self::{
+    auth::{protect, Clients},
+    config::{Api, Config},
+    poller::Registry,
+    shutdown::Shutdown,
+};
+use crate::client::{cache, keys};
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    pin_mut,
+};
+use std::sync::Arc;
+use tokio::timer::delay_for;
+use warp::{filters::BoxedFilter, Filter, Reply};
+
+pub type Routes = BoxedFilter<(Box<dyn Reply>,)>;
+
+fn not_found() -> Routes {
+    warp::any()
+        .and_then(|| async { Err::<Box<dyn Reply>, _>(warp::reject::not_found()) })
+        .boxed()
+}
+
+// Every live route subscribes through the same pollers. The APIs are only open to configured
+// clients, if there are any, while health checks, metrics and the OpenAPI document stay open.
+pub fn routes(config: &Config, pollers: Arc<Registry>, shutdown: Shutdown) -> Routes {
+    let clients = Arc::new(Clients::new(&config.clients));
+    let mut routes = health::routes(pollers.clone())
+        .or(not_found())
+        .unify()
+        .boxed();
+
+    if !clients.is_open() {
+        routes = auth::routes(clients.clone()).or(routes).unify().boxed();
+    }
+
+    if config.enabled(Api::Mirror) {
+        routes = protect(
+            clients.clone(),
+            Api::Mirror,
+            warp::path("api").boxed(),
+            mirror::routes(config.key()),
+        )
+        .or(routes)
+        .unify()
+        .boxed();
+    }
+
+    if config.enabled(Api::Sse) {
+        routes = protect(
+            clients.clone(),
+            Api::Sse,
+            warp::path("stations").boxed(),
+            sse::routes(pollers.clone(), shutdown.clone()),
+        )
+        .or(routes)
+        .unify()
+        .boxed();
+    }
+
+    if config.enabled(Api::Rest) {
+        routes = protect(
+            clients.clone(),
+            Api::Rest,
+            warp::path("v1").boxed(),
+            rest::routes(config.key()),
+        )
+        .or(routes)
+        .unify()
+        .boxed();
+    }
+
+    // Deliveries go out from a task of their own, polling BART only for the events webhooks want
+    #[cfg(feature = "webhooks")]
+    {
+        if config.enabled(Api::Webhooks) {
+            let delivery = webhooks::delivery::Delivery::new(
+                webhooks::delivery::retry_policy(config.webhook_retries),
+                webhooks::delivery::DeadLetters::new(config.webhook_dead_letters.clone()),
+                config.webhook_allowed_hosts.clone(),
+            );
+            let hooks = Arc::new(webhooks::Webhooks::new(delivery, shutdown.clone()));
+            tokio::spawn(webhooks::run(
+                hooks.clone(),
+                pollers.clone(),
+                shutdown.clone(),
+            ));
+            routes = protect(
+                clients.clone(),
+                Api::Webhooks,
+                warp::path("webhooks").boxed(),
+                webhooks::routes(hooks, clients.clone()),
+            )
+            .or(routes)
+            .unify()
+            .boxed();
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    {
+        routes = metrics::routes(pollers.clone()).or(routes).unify().boxed();
+    }
+
+    #[cfg(feature = "openapi")]
+    {
+        if config.enabled(Api::OpenApi) {
+            routes = rest::openapi::routes().or(routes).unify().boxed();
+        }
+    }
+
+    // Websockets authenticate when they initialize rather than when they upgrade
+    #[cfg(feature = "graphql")]
+    {
+        if config.enabled(Api::GraphQL) {
+            let schema = Arc::new(graphql::schema());
+            let limits = graphql::complexity::Limits::new(
+                schema.clone(),
+                config.max_query_depth,
+                config.max_query_cost,
+                &config.field_costs,
+            );
+            let limits = Arc::new(limits);
+            let context = graphql::Context::new(config.key(), pollers.clone());
+            let services = graphql::ws::Services {
+                coordinator: Arc::new(graphql::coordinator()),
+                limits: limits.clone(),
+                clients: clients.clone(),
+                shutdown,
+            };
+            routes = protect(
+                clients,
+                Api::GraphQL,
+                warp::path("graphql").and(warp::post2()).boxed(),
+                graphql::routes(schema, context.clone(), limits),
+            )
+            .or(graphql::subscriptions(context, services))
+            .unify()
+            .or(routes)
+            .unify()
+            .boxed();
+        }
+    }
+
+    routes
+}
+
+// On ctrl-c or SIGTERM the server stops accepting connections, tells live subscriptions it is
+// going away and stops polling BART, then waits up to `shutdown_timeout` for connections to close
+pub async fn serve(config: Config) {
+    cache::global().set_capacity(config.cache_capacity);
+    keys::global().set_keys(config.keys.clone());
+    let pollers = Arc::new(Registry::new(config.key(), config.intervals.clone()));
+    let (trigger, shutdown) = shutdown::channel();
+
+    let server: BoxFuture<'static, ()> = match config.mode {
+        #[cfg(feature = "metrics")]
+        config::Mode::Exporter => {
+            log::info!("Exporting BART data on {}", config.bind);
+            let config = config.clone();
+            let pollers = pollers.clone();
+            let shutdown = shutdown.clone();
+            async move { exporter::serve(&config, pollers, shutdown).await }.boxed()
+        }
+        _ => {
+            log::info!("Listening on {}", config.bind);
+            tokio::spawn(health::probe_version(config.key()));
+            let (_, server) = warp::serve(routes(&config, pollers.clone(), shutdown.clone()))
+                .bind_with_graceful_shutdown(config.bind, shutdown.clone().wait());
+            server.boxed()
+        }
+    };
+    // The listener closes as soon as shutdown starts, but upgraded websockets and event streams
+    // are still flushing their last frames
+    let closed = async move {
+        server.await;
+        shutdown.drained().await;
+    };
+
+    let timeout = config.shutdown_timeout;
+    let deadline = async move {
+        shutdown::signal().await;
+        log::info!(
+            "Shutting down, waiting up to {}s for connections to close",
+            timeout.as_secs()
+        );
+        trigger.shutdown();
+        pollers.stop_all();
+        delay_for(timeout).await;
+        log::warn!("Connections were still open at the shutdown deadline");
+        trigger.expire();
+    };
+
+    pin_mut!(closed);
+    pin_mut!(deadline);
+    future::select(closed, deadline).await;
+    log::logger().flush();
+}
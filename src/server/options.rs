@@ -0,0 +1,85 @@
+// Turns loose request parameters into the client's option types, shared by every server API
+use crate::client::{
+    apis::{
+        real_time_estimates::etd::{EtdOptions, EtdOptionsPlatform},
+        route_information::{
+            routeinfo::{RouteInfoOptions, RouteInfoOptionsDate},
+            routes::{RoutesOptions, RoutesOptionsDate},
+        },
+        schedule_information::arrive::ArriveTripsOptions,
+    },
+    constants::{direction::Direction, station::Station},
+};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveTime};
+
+pub const TIME_FORMAT: &str = "%H:%M";
+
+pub fn etd_options(
+    station: Option<Station>,
+    direction: Option<Direction>,
+    platform: Option<u8>,
+) -> Result<EtdOptions> {
+    let platform = match platform {
+        Some(platform) => Some(EtdOptionsPlatform::from_number(platform)?),
+        None => None,
+    };
+    EtdOptions::from_parts(station, direction, platform)
+}
+
+pub fn trips_options(before: Option<u8>, after: Option<u8>) -> Result<Option<ArriveTripsOptions>> {
+    if before.is_none() && after.is_none() {
+        return Ok(None);
+    }
+    let (default_before, default_after) = ArriveTripsOptions::default().as_a_b();
+    Ok(Some(ArriveTripsOptions::from_a_b(
+        before.unwrap_or(default_before),
+        after.unwrap_or(default_after),
+    )?))
+}
+
+pub fn time_option<T: AsRef<str>>(time: Option<T>) -> Result<Option<NaiveTime>> {
+    match time {
+        Some(time) => Ok(Some(NaiveTime::parse_from_str(time.as_ref(), TIME_FORMAT)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn routes_options(
+    schedule: Option<u8>,
+    date: Option<NaiveDate>,
+) -> Result<Option<RoutesOptions>> {
+    match (schedule, date) {
+        (Some(_), Some(_)) => Err(anyhow!("Use either `schedule` or `date`")),
+        (Some(schedule), None) => Ok(Some(RoutesOptions::Schedule(schedule))),
+        (None, Some(date)) => Ok(Some(RoutesOptions::Date(RoutesOptionsDate::Date(date)))),
+        (None, None) => Ok(None),
+    }
+}
+
+pub fn route_info_options(
+    schedule: Option<u8>,
+    date: Option<NaiveDate>,
+) -> Result<Option<RouteInfoOptions>> {
+    Ok(
+        routes_options(schedule, date)?.map(|options| match options {
+            RoutesOptions::Schedule(schedule) => RouteInfoOptions::Schedule(schedule),
+            RoutesOptions::Date(RoutesOptionsDate::Today) => {
+                RouteInfoOptions::Date(RouteInfoOptionsDate::Today)
+            }
+            RoutesOptions::Date(RoutesOptionsDate::Date(date)) => {
+                RouteInfoOptions::Date(RouteInfoOptionsDate::Date(date))
+            }
+        }),
+    )
+}
+
+#[test]
+fn trips_options_defaults() {
+    assert_eq!(trips_options(None, None).unwrap(), None);
+    assert_eq!(
+        trips_options(Some(0), None).unwrap(),
+        Some(ArriveTripsOptions::ZeroBeforeTwoAfter)
+    );
+    assert!(trips_options(Some(4), Some(4)).is_err());
+}
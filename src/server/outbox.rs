@@ -0,0 +1,218 @@
+use futures::{
+    future::poll_fn,
+    stream::Stream,
+    task::{Context, Poll, Waker},
+};
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+pub const DEFAULT_CAPACITY: usize = 64;
+pub const DEFAULT_MAX_DROPPED: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pushed {
+    Queued,
+    // A queued update with the same key was replaced before the client got to it
+    Replaced,
+    // Nothing is read from the outbox anymore
+    Closed,
+}
+
+// The client fell too far behind: either `capacity` messages are waiting or more than
+// `max_dropped` updates in a row were replaced before it read any. The backlog is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+struct Inner<T> {
+    queue: VecDeque<(Option<String>, T)>,
+    capacity: usize,
+    max_dropped: usize,
+    dropped: usize,
+    closed: bool,
+    receiver: Option<Waker>,
+    senders: Vec<Waker>,
+}
+
+impl<T> Inner<T> {
+    fn wake_receiver(&mut self) {
+        if let Some(waker) = self.receiver.take() {
+            waker.wake();
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.wake_receiver();
+        for waker in self.senders.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+// A bounded, per-connection queue between the tasks producing messages and the one writing them
+// to the client. Keyed updates are snapshots, so a newer one replaces any still waiting.
+pub struct Sender<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+pub fn channel<T>(capacity: usize, max_dropped: usize) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        queue: VecDeque::new(),
+        capacity,
+        max_dropped,
+        dropped: 0,
+        closed: false,
+        receiver: None,
+        senders: Vec::new(),
+    }));
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner },
+    )
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Sender {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    pub fn push(&self, item: T) -> Result<Pushed, Overflow> {
+        self.enqueue(None, item)
+    }
+
+    pub fn push_latest(&self, key: String, item: T) -> Result<Pushed, Overflow> {
+        self.enqueue(Some(key), item)
+    }
+
+    fn enqueue(&self, key: Option<String>, item: T) -> Result<Pushed, Overflow> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            return Ok(Pushed::Closed);
+        }
+
+        if key.is_some() {
+            if let Some(queued) = inner.queue.iter_mut().find(|(queued, _)| queued == &key) {
+                queued.1 = item;
+                inner.dropped += 1;
+                if inner.dropped > inner.max_dropped {
+                    inner.queue.clear();
+                    return Err(Overflow);
+                }
+                return Ok(Pushed::Replaced);
+            }
+        }
+
+        if inner.queue.len() >= inner.capacity {
+            inner.queue.clear();
+            return Err(Overflow);
+        }
+        inner.queue.push_back((key, item));
+        inner.wake_receiver();
+        Ok(Pushed::Queued)
+    }
+
+    // `item` is still delivered after everything already queued, however full the queue is
+    pub fn close_with(&self, item: T) {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.closed {
+            inner.queue.push_back((None, item));
+            inner.close();
+        }
+    }
+
+    pub fn close(&self) {
+        self.inner.lock().unwrap().close();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().closed
+    }
+
+    // Resolves once the outbox is closed or its receiver is gone
+    pub async fn closed(&self) {
+        poll_fn(|cx| {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.closed {
+                return Poll::Ready(());
+            }
+            if !inner
+                .senders
+                .iter()
+                .any(|waker| waker.will_wake(cx.waker()))
+            {
+                inner.senders.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.queue.pop_front() {
+            Some((_, item)) => {
+                inner.dropped = 0;
+                Poll::Ready(Some(item))
+            }
+            None if inner.closed => Poll::Ready(None),
+            None => {
+                inner.receiver = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.queue.clear();
+        inner.close();
+    }
+}
+
+#[tokio::test]
+async fn latest_update_wins() {
+    use futures::StreamExt;
+
+    let (sender, mut receiver) = channel(2, 1);
+    assert_eq!(sender.push_latest(String::from("1"), 1), Ok(Pushed::Queued));
+    assert_eq!(sender.push(10), Ok(Pushed::Queued));
+    assert_eq!(
+        sender.push_latest(String::from("1"), 2),
+        Ok(Pushed::Replaced)
+    );
+    assert_eq!(receiver.next().await, Some(2));
+    assert_eq!(receiver.next().await, Some(10));
+
+    assert_eq!(sender.push_latest(String::from("1"), 3), Ok(Pushed::Queued));
+    assert_eq!(
+        sender.push_latest(String::from("1"), 4),
+        Ok(Pushed::Replaced)
+    );
+    assert_eq!(sender.push_latest(String::from("1"), 5), Err(Overflow));
+
+    sender.close_with(20);
+    assert_eq!(receiver.next().await, Some(20));
+    assert_eq!(receiver.next().await, None);
+    assert_eq!(sender.push(30), Ok(Pushed::Closed));
+}
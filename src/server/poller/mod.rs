@@ -0,0 +1,232 @@
+pub mod pollers;
+
+use self::pollers::{Pollers, Subscriber};
+use crate::client::{
+    apis::{
+        advisories::{
+            bsa::{self, BsaResponse},
+            elev::{self, ElevResponse},
+        },
+        real_time_estimates::etd::{self, EtdOptions, EtdResponse, EtdStation},
+    },
+    constants::station::Station,
+};
+use anyhow::Result;
+use futures::{
+    future::Future,
+    stream::{self, BoxStream, StreamExt},
+};
+use std::{sync::Arc, time::Duration};
+use tokio::timer::delay_for;
+
+pub const DEFAULT_ETD_INTERVAL: Duration = Duration::from_secs(15);
+pub const DEFAULT_ADVISORY_INTERVAL: Duration = Duration::from_secs(60);
+
+pub const BSA_POLLER: &str = "bsa";
+pub const ELEV_POLLER: &str = "elev";
+
+pub fn etd_poller(options: &EtdOptions) -> String {
+    format!(
+        "etd/{}",
+        options.station().map(Station::to_abbr).unwrap_or("all")
+    )
+}
+
+// Calls `fetch` right away and then every `interval`, only yielding values whose `key` differs
+// from the last one yielded. Failed calls are logged and retried on the next tick.
+pub fn poll_changes<T, K, F, Fut, G>(
+    name: String,
+    interval: Duration,
+    fetch: F,
+    key: G,
+) -> BoxStream<'static, T>
+where
+    T: Send + 'static,
+    K: PartialEq + Send + 'static,
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+    G: Fn(&T) -> K + Send + Sync + 'static,
+{
+    let fetch = Arc::new(fetch);
+    let key = Arc::new(key);
+    stream::unfold((None, false), move |(mut last, mut wait)| {
+        let name = name.clone();
+        let fetch = fetch.clone();
+        let key = key.clone();
+        async move {
+            loop {
+                if wait {
+                    delay_for(interval).await;
+                }
+                wait = true;
+
+                match fetch().await {
+                    Ok(value) => {
+                        #[cfg(feature = "metrics")]
+                        crate::server::metrics::observe_poll(&name);
+                        let current = key(&value);
+                        if last.as_ref() != Some(&current) {
+                            last = Some(current);
+                            return Some((value, (last, wait)));
+                        }
+                    }
+                    Err(error) => log::warn!("Polling {} failed: {:#}", name, error),
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+// BART leaves a station out of the response entirely when nothing is departing from it
+pub fn departures_station(station: &Station, response: EtdResponse) -> EtdStation {
+    response
+        .station
+        .into_iter()
+        .find(|etd_station| &etd_station.abbr == station)
+        .unwrap_or_else(|| EtdStation {
+            name: String::from(station.to_full()),
+            abbr: station.clone(),
+            etd: vec![],
+        })
+}
+
+// Departures only count as changed when the estimates do, not just the response time
+pub fn etd(
+    options: EtdOptions,
+    key: Option<String>,
+    interval: Duration,
+) -> BoxStream<'static, EtdResponse> {
+    poll_changes(
+        etd_poller(&options),
+        interval,
+        move || {
+            let options = options.clone();
+            let key = key.clone();
+            async move { etd::call(&options, key).await }
+        },
+        |response: &EtdResponse| response.station.clone(),
+    )
+}
+
+// Advisories only count as changed when one appears, changes or is cleared
+pub fn bsa(key: Option<String>, interval: Duration) -> BoxStream<'static, BsaResponse> {
+    poll_changes(
+        String::from(BSA_POLLER),
+        interval,
+        move || {
+            let key = key.clone();
+            async move { bsa::call(key).await }
+        },
+        |response: &BsaResponse| response.bsa.clone(),
+    )
+}
+
+pub fn elev(key: Option<String>, interval: Duration) -> BoxStream<'static, ElevResponse> {
+    poll_changes(
+        String::from(ELEV_POLLER),
+        interval,
+        move || {
+            let key = key.clone();
+            async move { elev::call(key).await }
+        },
+        |response: &ElevResponse| response.bsa.clone(),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollIntervals {
+    pub etd: Duration,
+    pub bsa: Duration,
+    pub elev: Duration,
+    pub count: Duration,
+}
+
+impl Default for PollIntervals {
+    fn default() -> Self {
+        PollIntervals {
+            etd: DEFAULT_ETD_INTERVAL,
+            bsa: DEFAULT_ADVISORY_INTERVAL,
+            elev: DEFAULT_ADVISORY_INTERVAL,
+            count: DEFAULT_ADVISORY_INTERVAL,
+        }
+    }
+}
+
+// The pollers every subscription shares, so that the number of upstream calls depends on how many
+// distinct things are being watched rather than on how many clients are watching
+pub struct Registry {
+    key: Option<String>,
+    intervals: PollIntervals,
+    etd: Pollers<EtdOptions, EtdResponse>,
+    bsa: Pollers<(), BsaResponse>,
+    elev: Pollers<(), ElevResponse>,
+}
+
+impl Registry {
+    pub fn new(key: Option<String>, intervals: PollIntervals) -> Registry {
+        Registry {
+            key,
+            intervals,
+            etd: Pollers::new(),
+            bsa: Pollers::new(),
+            elev: Pollers::new(),
+        }
+    }
+
+    pub fn etd(&self, options: EtdOptions) -> Subscriber<EtdOptions, EtdResponse> {
+        let key = self.key.clone();
+        let interval = self.intervals.etd;
+        self.etd
+            .subscribe(options.clone(), move || etd(options, key, interval))
+    }
+
+    pub fn bsa(&self) -> Subscriber<(), BsaResponse> {
+        let key = self.key.clone();
+        let interval = self.intervals.bsa;
+        self.bsa.subscribe((), move || bsa(key, interval))
+    }
+
+    pub fn elev(&self) -> Subscriber<(), ElevResponse> {
+        let key = self.key.clone();
+        let interval = self.intervals.elev;
+        self.elev.subscribe((), move || elev(key, interval))
+    }
+
+    pub fn intervals(&self) -> &PollIntervals {
+        &self.intervals
+    }
+
+    pub fn etd_subscribers(&self) -> Vec<(EtdOptions, usize)> {
+        self.etd.subscribers()
+    }
+
+    // The names every running poller reports under
+    pub fn active(&self) -> Vec<String> {
+        let mut active: Vec<String> = self
+            .etd
+            .subscribers()
+            .iter()
+            .map(|(options, _)| etd_poller(options))
+            .collect();
+        if !self.bsa.is_empty() {
+            active.push(String::from(BSA_POLLER));
+        }
+        if !self.elev.is_empty() {
+            active.push(String::from(ELEV_POLLER));
+        }
+        active
+    }
+
+    pub fn stop_all(&self) {
+        self.etd.stop_all();
+        self.bsa.stop_all();
+        self.elev.stop_all();
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::new(None, PollIntervals::default())
+    }
+}
@@ -0,0 +1,181 @@
+use futures::{
+    future::{self, AbortHandle, Abortable},
+    stream::{BoxStream, Stream, StreamExt},
+    task::{Context, Poll},
+};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::watch;
+
+struct Poller<T> {
+    receiver: watch::Receiver<Option<T>>,
+    subscribers: usize,
+    handle: AbortHandle,
+}
+
+struct PollersInner<K, T> {
+    pollers: Mutex<HashMap<K, Poller<T>>>,
+}
+
+// One polling task per distinct key, shared by every subscriber of that key and stopped when
+// the last one goes away
+pub struct Pollers<K, T> {
+    inner: Arc<PollersInner<K, T>>,
+}
+
+impl<K, T> Pollers<K, T>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Pollers<K, T> {
+        Pollers {
+            inner: Arc::new(PollersInner {
+                pollers: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    // `start` is only called when nobody is polling `key` yet
+    pub fn subscribe<F>(&self, key: K, start: F) -> Subscriber<K, T>
+    where
+        F: FnOnce() -> BoxStream<'static, T>,
+    {
+        let mut pollers = self.inner.pollers.lock().unwrap();
+        let poller = pollers.entry(key.clone()).or_insert_with(|| {
+            let (tx, receiver) = watch::channel(None);
+            let (handle, registration) = AbortHandle::new_pair();
+            let task = start().for_each(move |value| {
+                // The registry holds on to a receiver, so this only fails once it's stopping
+                let _ = tx.broadcast(Some(value));
+                future::ready(())
+            });
+            tokio::spawn(async move {
+                let _ = Abortable::new(task, registration).await;
+            });
+            Poller {
+                receiver,
+                subscribers: 0,
+                handle,
+            }
+        });
+        poller.subscribers += 1;
+
+        Subscriber {
+            key,
+            receiver: poller.receiver.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.pollers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn subscribers(&self) -> Vec<(K, usize)> {
+        self.inner
+            .pollers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, poller)| (key.clone(), poller.subscribers))
+            .collect()
+    }
+
+    pub fn stop_all(&self) {
+        for (_, poller) in self.inner.pollers.lock().unwrap().drain() {
+            poller.handle.abort();
+        }
+    }
+}
+
+impl<K, T> Default for Pollers<K, T>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Pollers::new()
+    }
+}
+
+impl<K: Eq + Hash, T> PollersInner<K, T> {
+    fn unsubscribe(&self, key: &K) {
+        let mut pollers = self.pollers.lock().unwrap();
+        let last = match pollers.get_mut(key) {
+            Some(poller) => {
+                poller.subscribers -= 1;
+                poller.subscribers == 0
+            }
+            None => false,
+        };
+        if last {
+            if let Some(poller) = pollers.remove(key) {
+                poller.handle.abort();
+            }
+        }
+    }
+}
+
+// Yields the latest value right away, if there is one, and then every new value
+pub struct Subscriber<K: Eq + Hash, T> {
+    key: K,
+    receiver: watch::Receiver<Option<T>>,
+    inner: Arc<PollersInner<K, T>>,
+}
+
+impl<K: Eq + Hash, T> Unpin for Subscriber<K, T> {}
+
+impl<K: Eq + Hash, T: Clone> Stream for Subscriber<K, T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.receiver).poll_next(cx) {
+                Poll::Ready(Some(Some(value))) => return Poll::Ready(Some(value)),
+                Poll::Ready(Some(None)) => continue,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash, T> Drop for Subscriber<K, T> {
+    fn drop(&mut self) {
+        self.inner.unsubscribe(&self.key);
+    }
+}
+
+#[tokio::test]
+async fn pollers_are_shared() {
+    use futures::stream;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let pollers = Pollers::<&str, i32>::new();
+    let starts = AtomicUsize::new(0);
+    let start = || {
+        starts.fetch_add(1, Ordering::SeqCst);
+        stream::iter(vec![1]).chain(stream::pending()).boxed()
+    };
+
+    let mut first = pollers.subscribe("mont", start);
+    let mut second = pollers.subscribe("mont", start);
+    assert_eq!(first.next().await, Some(1));
+    assert_eq!(second.next().await, Some(1));
+    assert_eq!(starts.load(Ordering::SeqCst), 1);
+    assert_eq!(pollers.subscribers(), vec![("mont", 2)]);
+
+    drop(first);
+    drop(second);
+    assert!(pollers.is_empty());
+}
@@ -0,0 +1,280 @@
+#[cfg(feature = "openapi")]
+pub mod openapi;
+
+use crate::{
+    client::{
+        apis::{
+            advisories::{bsa, count, elev},
+            real_time_estimates::etd,
+            route_information::{routeinfo, routes},
+            schedule_information::{
+                arrive::{self, ArriveOptions},
+                depart::{self, DepartOptions},
+            },
+            station_information::{stnaccess, stninfo, stns},
+            version_information::version,
+        },
+        constants::{direction::Direction, station::Station},
+    },
+    server::{options, Routes},
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use warp::{http::StatusCode, Filter, Reply};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EtdQuery {
+    pub direction: Option<Direction>,
+    pub platform: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TripsQuery {
+    pub orig: Station,
+    pub dest: Station,
+    // "HH:MM", defaults to now
+    pub time: Option<String>,
+    pub date: Option<NaiveDate>,
+    pub before: Option<u8>,
+    pub after: Option<u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RoutesQuery {
+    pub schedule: Option<u8>,
+    pub date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+fn error_reply(status: StatusCode, error: anyhow::Error) -> Box<dyn Reply> {
+    let body = ErrorResponse {
+        error: format!("{:#}", error),
+    };
+    Box::new(warp::reply::with_status(warp::reply::json(&body), status))
+}
+
+// Bad parameters are the client's fault, anything that goes wrong after that is BART's
+async fn respond<T, F>(options: Result<F>) -> Box<dyn Reply>
+where
+    T: Serialize,
+    F: Future<Output = Result<T>>,
+{
+    let call = match options {
+        Ok(call) => call,
+        Err(error) => return error_reply(StatusCode::BAD_REQUEST, error),
+    };
+    match call.await {
+        Ok(response) => Box::new(warp::reply::json(&response)),
+        Err(error) => error_reply(StatusCode::BAD_GATEWAY, error),
+    }
+}
+
+fn station(abbr: &str) -> Result<Station> {
+    Station::from_abbr(abbr)
+}
+
+fn arrive_options(query: TripsQuery) -> Result<ArriveOptions> {
+    Ok(ArriveOptions {
+        time: options::time_option(query.time)?,
+        trips: options::trips_options(query.before, query.after)?,
+        orig: query.orig,
+        dest: query.dest,
+        date: query.date,
+    })
+}
+
+fn depart_options(query: TripsQuery) -> Result<DepartOptions> {
+    Ok(DepartOptions {
+        time: options::time_option(query.time)?,
+        trips: options::trips_options(query.before, query.after)?,
+        orig: query.orig,
+        dest: query.dest,
+        date: query.date,
+    })
+}
+
+pub fn routes(key: Option<String>) -> Routes {
+    let key = warp::any().map(move || key.clone());
+    let v1 = warp::path("v1");
+
+    let etd_all = v1
+        .and(warp::path("etd"))
+        .and(warp::path::end())
+        .and(key.clone())
+        .and_then(|key: Option<String>| async move {
+            let options = options::etd_options(None, None, None);
+            let call = options.map(|options| async move { etd::call(&options, key).await });
+            Ok::<_, warp::Rejection>(respond(call).await)
+        });
+
+    let etd_station = v1
+        .and(warp::path("etd"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<EtdQuery>())
+        .and(key.clone())
+        .and_then(
+            |abbr: String, query: EtdQuery, key: Option<String>| async move {
+                let options = station(&abbr).and_then(|station| {
+                    options::etd_options(Some(station), query.direction, query.platform)
+                });
+                let call = options.map(|options| async move { etd::call(&options, key).await });
+                Ok::<_, warp::Rejection>(respond(call).await)
+            },
+        );
+
+    let arrive = v1
+        .and(warp::path("trips"))
+        .and(warp::path("arrive"))
+        .and(warp::path::end())
+        .and(warp::query::<TripsQuery>())
+        .and(key.clone())
+        .and_then(|query: TripsQuery, key: Option<String>| async move {
+            let call = arrive_options(query)
+                .map(|options| async move { arrive::call(&options, key).await });
+            Ok::<_, warp::Rejection>(respond(call).await)
+        });
+
+    let depart = v1
+        .and(warp::path("trips"))
+        .and(warp::path("depart"))
+        .and(warp::path::end())
+        .and(warp::query::<TripsQuery>())
+        .and(key.clone())
+        .and_then(|query: TripsQuery, key: Option<String>| async move {
+            let call = depart_options(query)
+                .map(|options| async move { depart::call(&options, key).await });
+            Ok::<_, warp::Rejection>(respond(call).await)
+        });
+
+    let stations = v1
+        .and(warp::path("stations"))
+        .and(warp::path::end())
+        .and(key.clone())
+        .and_then(|key: Option<String>| async move {
+            Ok::<_, warp::Rejection>(respond(Ok(stns::call(key))).await)
+        });
+
+    let station_info = v1
+        .and(warp::path("stations"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(key.clone())
+        .and_then(|abbr: String, key: Option<String>| async move {
+            let call = station(&abbr).map(|station| stninfo::call(station, key));
+            Ok::<_, warp::Rejection>(respond(call).await)
+        });
+
+    let station_access = v1
+        .and(warp::path("stations"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("access"))
+        .and(warp::path::end())
+        .and(key.clone())
+        .and_then(|abbr: String, key: Option<String>| async move {
+            let call = station(&abbr).map(|station| stnaccess::call(station, key));
+            Ok::<_, warp::Rejection>(respond(call).await)
+        });
+
+    let all_routes = v1
+        .and(warp::path("routes"))
+        .and(warp::path::end())
+        .and(warp::query::<RoutesQuery>())
+        .and(key.clone())
+        .and_then(|query: RoutesQuery, key: Option<String>| async move {
+            let call = options::routes_options(query.schedule, query.date)
+                .map(|options| async move { routes::call(&options, key).await });
+            Ok::<_, warp::Rejection>(respond(call).await)
+        });
+
+    let route_info = v1
+        .and(warp::path("routes"))
+        .and(warp::path::param::<u8>())
+        .and(warp::path::end())
+        .and(warp::query::<RoutesQuery>())
+        .and(key.clone())
+        .and_then(
+            |route: u8, query: RoutesQuery, key: Option<String>| async move {
+                let call = options::route_info_options(query.schedule, query.date)
+                    .map(|options| async move { routeinfo::call(route, &options, key).await });
+                Ok::<_, warp::Rejection>(respond(call).await)
+            },
+        );
+
+    let advisories = v1
+        .and(warp::path("advisories"))
+        .and(warp::path::end())
+        .and(key.clone())
+        .and_then(|key: Option<String>| async move {
+            Ok::<_, warp::Rejection>(respond(Ok(bsa::call(key))).await)
+        });
+
+    let elevators = v1
+        .and(warp::path("elevators"))
+        .and(warp::path::end())
+        .and(key.clone())
+        .and_then(|key: Option<String>| async move {
+            Ok::<_, warp::Rejection>(respond(Ok(elev::call(key))).await)
+        });
+
+    let train_count = v1
+        .and(warp::path("count"))
+        .and(warp::path::end())
+        .and(key.clone())
+        .and_then(|key: Option<String>| async move {
+            Ok::<_, warp::Rejection>(respond(Ok(count::call(key))).await)
+        });
+
+    let api_version = v1
+        .and(warp::path("version"))
+        .and(warp::path::end())
+        .and(key)
+        .and_then(|key: Option<String>| async move {
+            Ok::<_, warp::Rejection>(respond(Ok(version::call(key))).await)
+        });
+
+    warp::get2()
+        .and(
+            etd_all
+                .or(etd_station)
+                .unify()
+                .or(arrive)
+                .unify()
+                .or(depart)
+                .unify()
+                .or(stations)
+                .unify()
+                .or(station_info)
+                .unify()
+                .or(station_access)
+                .unify()
+                .or(all_routes)
+                .unify()
+                .or(route_info)
+                .unify()
+                .or(advisories)
+                .unify()
+                .or(elevators)
+                .unify()
+                .or(train_count)
+                .unify()
+                .or(api_version)
+                .unify(),
+        )
+        .boxed()
+}
+
+#[tokio::test]
+async fn unknown_station_is_bad_request() {
+    let response = warp::test::request()
+        .path("/v1/etd/nope")
+        .reply(&routes(None))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,257 @@
+pub mod schemas;
+
+use crate::{
+    client::apis::{
+        advisories::{bsa::BsaResponse, count::Count, elev::ElevResponse},
+        real_time_estimates::etd::EtdResponse,
+        route_information::{routeinfo::RouteInfoResponse, routes::RoutesResponse},
+        schedule_information::{arrive::ArriveResponse, depart::DepartResponse},
+        station_information::{stnaccess, stninfo, stns},
+        version_information::version::Version,
+    },
+    server::Routes,
+};
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use warp::{Filter, Reply};
+
+pub const OPENAPI_VERSION: &str = "3.0.0";
+
+struct Document {
+    generator: schemars::gen::SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+fn parameter(
+    name: &str,
+    location: &str,
+    required: bool,
+    schema: Value,
+    description: &str,
+) -> Value {
+    json!({
+        "name": name,
+        "in": location,
+        "required": required,
+        "description": description,
+        "schema": schema,
+    })
+}
+
+fn station_parameter() -> Value {
+    parameter(
+        "station",
+        "path",
+        true,
+        json!({ "$ref": "#/components/schemas/StationCode" }),
+        "A station abbreviation",
+    )
+}
+
+fn trips_parameters() -> Vec<Value> {
+    let station = json!({ "$ref": "#/components/schemas/StationCode" });
+    vec![
+        parameter("orig", "query", true, station.clone(), "Origin station"),
+        parameter("dest", "query", true, station, "Destination station"),
+        parameter(
+            "time",
+            "query",
+            false,
+            json!({ "type": "string" }),
+            "\"HH:MM\", defaults to now",
+        ),
+        parameter(
+            "date",
+            "query",
+            false,
+            json!({ "type": "string", "format": "date" }),
+            "Defaults to today",
+        ),
+        parameter(
+            "before",
+            "query",
+            false,
+            json!({ "type": "integer", "minimum": 0, "maximum": 4 }),
+            "Trips before `time`",
+        ),
+        parameter(
+            "after",
+            "query",
+            false,
+            json!({ "type": "integer", "minimum": 1, "maximum": 4 }),
+            "Trips after `time`",
+        ),
+    ]
+}
+
+fn routes_parameters() -> Vec<Value> {
+    vec![
+        parameter(
+            "schedule",
+            "query",
+            false,
+            json!({ "type": "integer" }),
+            "Schedule number",
+        ),
+        parameter(
+            "date",
+            "query",
+            false,
+            json!({ "type": "string", "format": "date" }),
+            "Use the schedule in effect on this date",
+        ),
+    ]
+}
+
+impl Document {
+    fn new() -> Document {
+        Document {
+            generator: SchemaSettings::openapi3().into_generator(),
+            paths: Map::new(),
+        }
+    }
+
+    fn get<T: JsonSchema>(&mut self, path: &str, summary: &str, parameters: Vec<Value>) {
+        let schema = self.generator.subschema_for::<T>();
+        self.paths.insert(
+            String::from(path),
+            json!({
+                "get": {
+                    "summary": summary,
+                    "parameters": parameters,
+                    "responses": {
+                        "200": {
+                            "description": "OK",
+                            "content": { "application/json": { "schema": schema } },
+                        },
+                        "400": { "$ref": "#/components/responses/BadRequest" },
+                        "502": { "$ref": "#/components/responses/BadGateway" },
+                    },
+                },
+            }),
+        );
+    }
+
+    fn into_value(mut self) -> Value {
+        // Path parameters refer to the station schema even when no response does
+        self.generator
+            .subschema_for::<crate::client::constants::station::Station>();
+        let error = json!({
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "type": "object",
+                        "properties": { "error": { "type": "string" } },
+                        "required": ["error"],
+                    },
+                },
+            },
+        });
+        let mut bad_request = error.clone();
+        bad_request["description"] = json!("The parameters are invalid");
+        let mut bad_gateway = error;
+        bad_gateway["description"] = json!("BART could not be reached or returned an error");
+
+        json!({
+            "openapi": OPENAPI_VERSION,
+            "info": {
+                "title": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "paths": self.paths,
+            "components": {
+                "schemas": self.generator.definitions(),
+                "responses": {
+                    "BadRequest": bad_request,
+                    "BadGateway": bad_gateway,
+                },
+            },
+        })
+    }
+}
+
+// Generated from the same types the REST routes serialize, so the two can't drift apart
+pub fn document() -> Value {
+    let mut document = Document::new();
+    document.get::<EtdResponse>("/v1/etd", "Real-time departures from every station", vec![]);
+    document.get::<EtdResponse>(
+        "/v1/etd/{station}",
+        "Real-time departures from a station",
+        vec![
+            station_parameter(),
+            parameter(
+                "direction",
+                "query",
+                false,
+                json!({ "$ref": "#/components/schemas/Direction" }),
+                "Only trains going this way",
+            ),
+            parameter(
+                "platform",
+                "query",
+                false,
+                json!({ "type": "integer", "minimum": 1, "maximum": 4 }),
+                "Only trains on this platform",
+            ),
+        ],
+    );
+    document.get::<ArriveResponse>(
+        "/v1/trips/arrive",
+        "Trips arriving by a time",
+        trips_parameters(),
+    );
+    document.get::<DepartResponse>(
+        "/v1/trips/depart",
+        "Trips leaving at a time",
+        trips_parameters(),
+    );
+    document.get::<stns::StationsResponse>("/v1/stations", "Every station", vec![]);
+    document.get::<stninfo::StationsResponse>(
+        "/v1/stations/{station}",
+        "Information about a station",
+        vec![station_parameter()],
+    );
+    document.get::<stnaccess::StationsResponse>(
+        "/v1/stations/{station}/access",
+        "Access information for a station",
+        vec![station_parameter()],
+    );
+    document.get::<RoutesResponse>("/v1/routes", "Every route", routes_parameters());
+    let mut route_parameters = vec![parameter(
+        "route",
+        "path",
+        true,
+        json!({ "type": "integer" }),
+        "Route number",
+    )];
+    route_parameters.extend(routes_parameters());
+    document.get::<RouteInfoResponse>(
+        "/v1/routes/{route}",
+        "Information about a route",
+        route_parameters,
+    );
+    document.get::<BsaResponse>("/v1/advisories", "Service advisories", vec![]);
+    document.get::<ElevResponse>("/v1/elevators", "Elevator outages", vec![]);
+    document.get::<Count>("/v1/count", "Trains in service", vec![]);
+    document.get::<Version>("/v1/version", "BART API version", vec![]);
+    document.into_value()
+}
+
+pub fn routes() -> Routes {
+    let document = Arc::new(document());
+    warp::path("openapi.json")
+        .and(warp::path::end())
+        .and(warp::get2())
+        .map(move || Box::new(warp::reply::json(&*document)) as Box<dyn Reply>)
+        .boxed()
+}
+
+#[test]
+fn document_covers_rest_routes() {
+    let document = document();
+    assert_eq!(document["openapi"], OPENAPI_VERSION);
+    assert!(document["paths"]["/v1/etd/{station}"]["get"].is_object());
+    assert!(document["components"]["schemas"]["EtdResponse"].is_object());
+    assert!(document["components"]["schemas"]["StationCode"].is_object());
+}
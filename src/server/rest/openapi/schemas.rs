@@ -0,0 +1,94 @@
+use crate::client::{
+    apis::{
+        advisories::{bsa::r#type::BsaType, elev::r#type::ElevType},
+        real_time_estimates::etd::minutes::EtdEstimateMinutes,
+    },
+    constants::{
+        color::Color,
+        datetime::{Date, DateTime, Time},
+        direction::{Direction, DIRECTION_CODE_NORTHBOUND, DIRECTION_CODE_SOUTHBOUND},
+        fare_type::FareType,
+        station::Station,
+    },
+};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject},
+    JsonSchema,
+};
+
+// Every constant serializes to a plain string, described the same way as its GraphQL scalar
+
+fn string_schema(description: &str, values: Option<&[&str]>) -> Schema {
+    SchemaObject {
+        metadata: Some(Box::new(Metadata {
+            description: Some(String::from(description)),
+            ..Default::default()
+        })),
+        instance_type: Some(InstanceType::String.into()),
+        enum_values: values.map(|values| values.iter().map(|value| (*value).into()).collect()),
+        ..Default::default()
+    }
+    .into()
+}
+
+macro_rules! string_schema {
+    ($type:ty, $name:expr, $description:expr) => {
+        string_schema!($type, $name, $description, None);
+    };
+    ($type:ty, $name:expr, $description:expr, $values:expr) => {
+        impl JsonSchema for $type {
+            fn schema_name() -> String {
+                String::from($name)
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                string_schema($description, $values)
+            }
+        }
+    };
+}
+
+string_schema!(
+    Station,
+    "StationCode",
+    "A BART station abbreviation, e.g. \"mont\""
+);
+string_schema!(
+    Direction,
+    "Direction",
+    "A direction of travel, either \"n\" or \"s\"",
+    Some(&[DIRECTION_CODE_NORTHBOUND, DIRECTION_CODE_SOUTHBOUND])
+);
+string_schema!(
+    Color,
+    "Color",
+    "A route color as a hex code, e.g. \"#ffff33\""
+);
+string_schema!(FareType, "FareType", "A fare type, e.g. \"clipper\"");
+string_schema!(
+    BsaType,
+    "BsaType",
+    "A service advisory type, e.g. \"DELAY\""
+);
+string_schema!(
+    ElevType,
+    "ElevType",
+    "An elevator advisory type, e.g. \"ELEVATOR\""
+);
+string_schema!(
+    EtdEstimateMinutes,
+    "EtdEstimateMinutes",
+    "Minutes until departure, or \"Leaving\""
+);
+string_schema!(Date, "Date", "A date formatted as \"MM/DD/YYYY\"");
+string_schema!(
+    Time,
+    "Time",
+    "A time formatted as \"HH:MM:SS AM\", followed by the time zone when BART sends one"
+);
+string_schema!(
+    DateTime,
+    "DateTime",
+    "A date and time formatted as \"Mon Jan 01 2019 12:00 AM PST\""
+);
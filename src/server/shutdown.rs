@@ -0,0 +1,186 @@
+use futures::{future, pin_mut, StreamExt};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+// Websockets and event streams outlive the requests that opened them, so the server keeps count of
+// them itself
+struct Live {
+    count: Mutex<usize>,
+    sender: watch::Sender<usize>,
+    receiver: watch::Receiver<usize>,
+}
+
+// Counts as a live connection until dropped
+pub struct Connection {
+    live: Arc<Live>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let mut count = self.live.count.lock().unwrap();
+        *count -= 1;
+        // The receiver is kept in `live`, so this can't fail
+        let _ = self.live.sender.broadcast(*count);
+    }
+}
+
+// Run when the server stops waiting for connections, for work that would otherwise be lost
+type Hook = Box<dyn FnOnce() + Send>;
+
+// Resolves once shutdown has been triggered, for everything that has to wind down with the server
+#[derive(Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+    live: Arc<Live>,
+    hooks: Arc<Mutex<Vec<Hook>>>,
+}
+
+pub struct Trigger {
+    sender: watch::Sender<bool>,
+    hooks: Arc<Mutex<Vec<Hook>>>,
+}
+
+pub fn channel() -> (Trigger, Shutdown) {
+    let (sender, receiver) = watch::channel(false);
+    let (live_sender, live_receiver) = watch::channel(0);
+    let live = Arc::new(Live {
+        count: Mutex::new(0),
+        sender: live_sender,
+        receiver: live_receiver,
+    });
+    let hooks = Arc::new(Mutex::new(vec![]));
+    (
+        Trigger {
+            sender,
+            hooks: hooks.clone(),
+        },
+        Shutdown {
+            receiver,
+            live,
+            hooks,
+        },
+    )
+}
+
+impl Trigger {
+    pub fn shutdown(&self) {
+        // Nobody is left to tell if every receiver is gone
+        let _ = self.sender.broadcast(true);
+    }
+
+    // The deadline for connections to close has passed
+    pub fn expire(&self) {
+        let hooks: Vec<Hook> = self.hooks.lock().unwrap().drain(..).collect();
+        for hook in hooks {
+            hook();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn is_shutdown(&self) -> bool {
+        *self.receiver.get_ref()
+    }
+
+    pub async fn wait(mut self) {
+        while let Some(shutdown) = self.receiver.recv().await {
+            if shutdown {
+                return;
+            }
+        }
+    }
+
+    // To be held for as long as a websocket or event stream is open, including while it flushes
+    // its last frames
+    pub fn connection(&self) -> Connection {
+        let mut count = self.live.count.lock().unwrap();
+        *count += 1;
+        let _ = self.live.sender.broadcast(*count);
+        Connection {
+            live: self.live.clone(),
+        }
+    }
+
+    pub fn at_deadline(&self, hook: impl FnOnce() + Send + 'static) {
+        self.hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    // Resolves once no connections are left
+    pub async fn drained(&self) {
+        let mut receiver = self.live.receiver.clone();
+        loop {
+            if *receiver.get_ref() == 0 {
+                return;
+            }
+            if receiver.recv().await.is_none() {
+                return;
+            }
+        }
+    }
+}
+
+async fn ctrl_c() {
+    match tokio::net::signal::ctrl_c() {
+        Ok(mut ctrl_c) => {
+            ctrl_c.next().await;
+        }
+        Err(error) => {
+            log::error!("Could not listen for ctrl-c: {}", error);
+            future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn terminate() {
+    use tokio::net::signal::unix::{Signal, SignalKind};
+    match Signal::new(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            terminate.next().await;
+        }
+        Err(error) => {
+            log::error!("Could not listen for SIGTERM: {}", error);
+            future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate() {
+    future::pending::<()>().await;
+}
+
+// Resolves on ctrl-c or SIGTERM
+pub async fn signal() {
+    let ctrl_c = ctrl_c();
+    let terminate = terminate();
+    pin_mut!(ctrl_c, terminate);
+    future::select(ctrl_c, terminate).await;
+}
+
+#[tokio::test]
+async fn shutdown_wakes_waiters() {
+    let (trigger, shutdown) = channel();
+    assert!(!shutdown.is_shutdown());
+    let waiter = shutdown.clone().wait();
+    trigger.shutdown();
+    waiter.await;
+    assert!(shutdown.is_shutdown());
+
+    use std::time::Duration;
+    use tokio::future::FutureExt;
+    let connection = shutdown.connection();
+    assert!(shutdown
+        .drained()
+        .timeout(Duration::from_millis(10))
+        .await
+        .is_err());
+    drop(connection);
+    shutdown.drained().await;
+
+    let expired = Arc::new(Mutex::new(false));
+    let hook = expired.clone();
+    shutdown.at_deadline(move || *hook.lock().unwrap() = true);
+    trigger.expire();
+    assert!(*expired.lock().unwrap());
+}
@@ -0,0 +1,164 @@
+use crate::{
+    client::{
+        apis::real_time_estimates::etd::{EtdOptions, EtdStation},
+        constants::{direction::Direction, station::Station},
+    },
+    server::{
+        options::etd_options,
+        outbox::{self, Overflow, Pushed},
+        poller::{departures_station, Registry},
+        shutdown::Shutdown,
+        Routes,
+    },
+};
+use futures::{
+    future, pin_mut,
+    stream::{self, StreamExt},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use warp::{sse::ServerSentEvent, Filter, Reply};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeparturesQuery {
+    pub direction: Option<Direction>,
+    pub platform: Option<u8>,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// 64-bit FNV-1a, which unlike the standard library's hashers is the same in every build
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// Event ids are derived from the content rather than counted per connection, so a client that
+// reconnects to another subscriber of the same poller, or to a redeployed server, can still tell
+// whether it missed anything
+pub fn event_id(station: &EtdStation) -> String {
+    let json = serde_json::to_string(station).unwrap_or_default();
+    format!("{:016x}", fnv1a(json.as_bytes()))
+}
+
+const SNAPSHOT: &str = "departures";
+#[cfg(feature = "metrics")]
+const TRANSPORT: &str = "sse";
+
+// Feeds snapshots into the client's outbox until the client goes away or falls too far behind
+async fn forward(
+    snapshots: impl futures::Stream<Item = (String, EtdStation)> + Send + 'static,
+    outbox: outbox::Sender<(String, EtdStation)>,
+) {
+    let feed = snapshots.for_each(|snapshot| {
+        match outbox.push_latest(String::from(SNAPSHOT), snapshot) {
+            Ok(Pushed::Replaced) => {
+                #[cfg(feature = "metrics")]
+                crate::server::metrics::observe_dropped_update(TRANSPORT);
+            }
+            Ok(_) => {}
+            Err(Overflow) => {
+                #[cfg(feature = "metrics")]
+                crate::server::metrics::observe_slow_consumer(TRANSPORT);
+                outbox.close();
+            }
+        }
+        future::ready(())
+    });
+    let closed = outbox.closed();
+    pin_mut!(feed, closed);
+    future::select(feed, closed).await;
+    outbox.close();
+}
+
+// Skips any snapshot the client has already seen, starting from the one named by `Last-Event-ID`,
+// and ends the stream once the server starts shutting down
+fn departures(
+    pollers: &Registry,
+    station: Station,
+    options: EtdOptions,
+    last_event_id: Option<String>,
+    shutdown: Shutdown,
+) -> impl futures::Stream<Item = std::result::Result<impl ServerSentEvent, warp::Error>> + Send + 'static
+{
+    // Shutdown waits until the stream is dropped, once its last events are written
+    let live = shutdown.connection();
+    let mut last_event_id = last_event_id;
+    let updates = pollers
+        .etd(options)
+        .map(move |response| Some(departures_station(&station, response)));
+    let shutdown = stream::once(shutdown.wait()).map(|_| None);
+    let snapshots = stream::select(updates, shutdown.boxed())
+        .take_while(|station| future::ready(station.is_some()))
+        .filter_map(future::ready)
+        .filter_map(move |station| {
+            let id = event_id(&station);
+            if last_event_id.as_ref() == Some(&id) {
+                return future::ready(None);
+            }
+            last_event_id = Some(id.clone());
+            future::ready(Some((id, station)))
+        });
+
+    let (outbox, events) = outbox::channel(outbox::DEFAULT_CAPACITY, outbox::DEFAULT_MAX_DROPPED);
+    tokio::spawn(forward(snapshots, outbox));
+    events.map(move |(id, station)| {
+        let _live = &live;
+        Ok((warp::sse::id(id), warp::sse::json(station)))
+    })
+}
+
+pub fn routes(pollers: Arc<Registry>, shutdown: Shutdown) -> Routes {
+    warp::path!("stations" / String / "departures" / "stream")
+        .and(warp::get2())
+        .and(warp::query::<DeparturesQuery>())
+        .and(warp::sse::last_event_id::<String>())
+        .and(warp::sse())
+        .map(
+            move |abbr: String,
+                  query: DeparturesQuery,
+                  last_event_id: Option<String>,
+                  sse: warp::sse::Sse| {
+                let options = Station::from_abbr(&abbr).and_then(|station| {
+                    Ok((
+                        station.clone(),
+                        etd_options(Some(station), query.direction, query.platform)?,
+                    ))
+                });
+                match options {
+                    Ok((station, options)) => {
+                        let events =
+                            departures(&pollers, station, options, last_event_id, shutdown.clone());
+                        Box::new(sse.reply(warp::sse::keep(events, None))) as Box<dyn Reply>
+                    }
+                    Err(error) => Box::new(warp::reply::with_status(
+                        error.to_string(),
+                        warp::http::StatusCode::BAD_REQUEST,
+                    )) as Box<dyn Reply>,
+                }
+            },
+        )
+        .boxed()
+}
+
+#[test]
+fn event_id_is_stable() {
+    let station = EtdStation {
+        name: String::from(Station::Orinda.to_full()),
+        abbr: Station::Orinda,
+        etd: vec![],
+    };
+    assert_eq!(event_id(&station), event_id(&station.clone()));
+    assert_ne!(
+        event_id(&station),
+        event_id(&EtdStation {
+            abbr: Station::Embarcadero,
+            ..station
+        })
+    );
+    // Published test vectors, so ids don't change with the toolchain
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+}
@@ -0,0 +1,412 @@
+use super::{check_target, events::Event, Webhook};
+use crate::{client::retry::RetryPolicy, server::shutdown::Shutdown};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use futures::{future, pin_mut};
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::{future::FutureExt, timer::delay_for};
+use url::Url;
+
+pub const SIGNATURE_HEADER: &str = "x-sfbart-signature";
+pub const EVENT_HEADER: &str = "x-sfbart-event";
+pub const DELIVERY_HEADER: &str = "x-sfbart-delivery";
+
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_secs(1);
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+// Dead letters kept in memory for `/webhooks/dead-letters`, the log file keeps all of them
+pub const DEAD_LETTER_CAPACITY: usize = 100;
+
+#[cfg(feature = "metrics")]
+pub const OUTCOME_DELIVERED: &str = "delivered";
+#[cfg(feature = "metrics")]
+pub const OUTCOME_RETRIED: &str = "retried";
+#[cfg(feature = "metrics")]
+pub const OUTCOME_DEAD_LETTERED: &str = "dead_lettered";
+
+// Receivers get longer to recover than BART does
+pub fn retry_policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        base_delay: DEFAULT_BASE_DELAY,
+        max_delay: DEFAULT_MAX_DELAY,
+        timeout: DEFAULT_TIMEOUT,
+    }
+}
+
+pub fn random_token(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .collect()
+}
+
+// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`, so receivers can also turn away
+// deliveries replayed long after they were sent
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.input(format!("{}.{}", timestamp, body).as_bytes());
+    format!("t={},v1={}", timestamp, hex::encode(mac.result().code()))
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    id: &'a str,
+    webhook: &'a str,
+    created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub delivery: String,
+    pub webhook: String,
+    // The client that registered the webhook
+    pub client: Option<String>,
+    pub url: String,
+    pub event: String,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+    // Exactly what was sent, so it can be replayed by hand
+    pub body: String,
+}
+
+// Deliveries that ran out of retries, appended as JSON lines to `path` when one is configured
+pub struct DeadLetters {
+    recent: Mutex<VecDeque<DeadLetter>>,
+    path: Option<PathBuf>,
+}
+
+impl DeadLetters {
+    pub fn new(path: Option<PathBuf>) -> DeadLetters {
+        DeadLetters {
+            recent: Mutex::new(VecDeque::new()),
+            path,
+        }
+    }
+
+    fn append(&self, letter: &DeadLetter) -> Result<()> {
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", serde_json::to_string(letter)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn record(&self, letter: DeadLetter) {
+        log::error!(
+            "Gave up delivering {} to webhook {} after {} attempts: {}",
+            letter.delivery,
+            letter.webhook,
+            letter.attempts,
+            letter.error
+        );
+        if let Err(error) = self.append(&letter) {
+            log::error!("Could not write to the dead letter log: {:#}", error);
+        }
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() >= DEAD_LETTER_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(letter);
+    }
+
+    // Oldest first
+    pub fn recent(&self) -> Vec<DeadLetter> {
+        self.recent.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+enum Attempt {
+    Delivered,
+    Retry(anyhow::Error),
+    Fail(anyhow::Error),
+}
+
+pub struct Delivery {
+    client: reqwest::Client,
+    policy: RetryPolicy,
+    pub allowed_hosts: Vec<String>,
+    // What would be dead-lettered for each delivery still under way, by delivery id
+    in_flight: Mutex<HashMap<String, DeadLetter>>,
+    pub dead_letters: DeadLetters,
+}
+
+impl Delivery {
+    // Redirects aren't followed, since they could lead anywhere `check_target` wouldn't allow
+    pub fn new(
+        policy: RetryPolicy,
+        dead_letters: DeadLetters,
+        allowed_hosts: Vec<String>,
+    ) -> Delivery {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::RedirectPolicy::none())
+            .build()
+            .expect("The webhook HTTP client could not be built");
+        Delivery {
+            client,
+            policy,
+            allowed_hosts,
+            in_flight: Mutex::new(HashMap::new()),
+            dead_letters,
+        }
+    }
+
+    // Every attempt is signed anew, with the same body. The host is checked again each time, as
+    // what it resolves to can change after the webhook was registered.
+    async fn attempt(&self, webhook: &Webhook, id: &str, event: &Event, body: &str) -> Attempt {
+        let url = match Url::parse(&webhook.url) {
+            Ok(url) => url,
+            Err(error) => return Attempt::Fail(error.into()),
+        };
+        if let Err(error) = check_target(&url, &self.allowed_hosts).await {
+            return Attempt::Fail(error);
+        }
+        let signature = sign(&webhook.secret, Utc::now().timestamp(), body);
+        let request = self
+            .client
+            .post(url)
+            .header("content-type", "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(EVENT_HEADER, event.kind().to_code())
+            .header(DELIVERY_HEADER, id)
+            .body(String::from(body))
+            .send();
+        match request.timeout(self.policy.timeout).await {
+            Err(_) => Attempt::Retry(anyhow!("Timed out after {:?}", self.policy.timeout)),
+            Ok(Err(error)) => Attempt::Retry(error.into()),
+            Ok(Ok(response)) if response.status().is_success() => Attempt::Delivered,
+            // Receivers that don't want the event won't want it again either
+            Ok(Ok(response))
+                if response.status().is_client_error()
+                    && response.status().as_u16() != 408
+                    && response.status().as_u16() != 429 =>
+            {
+                Attempt::Fail(anyhow!("Receiver responded with {}", response.status()))
+            }
+            Ok(Ok(response)) => {
+                Attempt::Retry(anyhow!("Receiver responded with {}", response.status()))
+            }
+        }
+    }
+
+    // Stops retrying once the server starts shutting down
+    pub async fn deliver(&self, webhook: &Webhook, event: &Event, shutdown: Shutdown) {
+        let id = random_token(24);
+        let payload = Payload {
+            id: &id,
+            webhook: &webhook.id,
+            created_at: Utc::now(),
+            event,
+        };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(error) => {
+                log::error!("Could not serialize a webhook event: {}", error);
+                return;
+            }
+        };
+
+        self.in_flight.lock().unwrap().insert(
+            id.clone(),
+            DeadLetter {
+                delivery: id.clone(),
+                webhook: webhook.id.clone(),
+                client: webhook.client.clone(),
+                url: webhook.url.clone(),
+                event: String::from(event.kind().to_code()),
+                attempts: 0,
+                error: String::from("Not attempted yet"),
+                failed_at: Utc::now(),
+                body: body.clone(),
+            },
+        );
+
+        let mut retries = 0;
+        loop {
+            let attempt = self.attempt(webhook, &id, event, &body).await;
+            let error = match attempt {
+                Attempt::Delivered => {
+                    #[cfg(feature = "metrics")]
+                    crate::server::metrics::observe_webhook_delivery(OUTCOME_DELIVERED);
+                    self.in_flight.lock().unwrap().remove(&id);
+                    return;
+                }
+                Attempt::Retry(error) if retries < self.policy.max_retries => {
+                    #[cfg(feature = "metrics")]
+                    crate::server::metrics::observe_webhook_delivery(OUTCOME_RETRIED);
+                    log::warn!(
+                        "Delivering {} to webhook {} failed, retrying: {:#}",
+                        id,
+                        webhook.id,
+                        error
+                    );
+                    if let Some(letter) = self.in_flight.lock().unwrap().get_mut(&id) {
+                        letter.attempts = retries + 1;
+                        letter.error = format!("{:#}", error);
+                    }
+                    let backoff = delay_for(self.policy.backoff(retries));
+                    let stopped = shutdown.clone().wait();
+                    pin_mut!(backoff, stopped);
+                    if let future::Either::Right(_) = future::select(backoff, stopped).await {
+                        anyhow!("Server shut down before the next retry, after: {:#}", error)
+                    } else {
+                        retries += 1;
+                        continue;
+                    }
+                }
+                Attempt::Retry(error) | Attempt::Fail(error) => error,
+            };
+
+            // Already dead-lettered if the server gave up waiting for it
+            let letter = self.in_flight.lock().unwrap().remove(&id);
+            if let Some(letter) = letter {
+                self.dead_letter(DeadLetter {
+                    attempts: retries + 1,
+                    error: format!("{:#}", error),
+                    failed_at: Utc::now(),
+                    ..letter
+                });
+            }
+            return;
+        }
+    }
+
+    fn dead_letter(&self, letter: DeadLetter) {
+        #[cfg(feature = "metrics")]
+        crate::server::metrics::observe_webhook_delivery(OUTCOME_DEAD_LETTERED);
+        self.dead_letters.record(letter);
+    }
+
+    // Dead-letters every delivery still under way, for when the server won't wait for them
+    pub fn abandon(&self) {
+        let in_flight: Vec<DeadLetter> = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, letter)| letter)
+            .collect();
+        for letter in in_flight {
+            self.dead_letter(DeadLetter {
+                error: format!(
+                    "Server shut down before the delivery finished, after: {}",
+                    letter.error
+                ),
+                failed_at: Utc::now(),
+                ..letter
+            });
+        }
+    }
+}
+
+#[tokio::test]
+async fn delivers_signed_events_to_local_receiver() {
+    use super::events::Filter;
+    use crate::client::apis::advisories::elev::Elev;
+    use bytes::Buf;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use warp::{http::StatusCode, Filter as _};
+
+    // Fails the first delivery, then takes every other one
+    let received = Arc::new(Mutex::new(vec![]));
+    let requests = Arc::new(AtomicUsize::new(0));
+    let receiver = {
+        let received = received.clone();
+        warp::post2()
+            .and(warp::header::<String>(SIGNATURE_HEADER))
+            .and(warp::body::concat())
+            .map(move |signature: String, body: warp::body::FullBody| {
+                if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+                let body = String::from_utf8(body.bytes().to_vec()).unwrap();
+                received.lock().unwrap().push((signature, body));
+                StatusCode::NO_CONTENT
+            })
+    };
+    let (addr, server) = warp::serve(receiver).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let webhook = Webhook {
+        id: String::from("hook"),
+        client: None,
+        url: format!("http://{}/events", addr),
+        secret: String::from("secret"),
+        filter: Filter::default(),
+    };
+    let event = Event::ElevatorCleared {
+        outage: Elev {
+            id: Some(String::from("7")),
+            station: String::from("MONT"),
+            r#type: None,
+            description: String::from("Elevator out"),
+            sms_text: String::from("Elevator out"),
+            posted: None,
+            expires: None,
+        },
+    };
+    let policy = RetryPolicy {
+        max_retries: 1,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+        timeout: DEFAULT_TIMEOUT,
+    };
+    let delivery = Delivery::new(
+        policy,
+        DeadLetters::new(None),
+        vec![String::from("127.0.0.1")],
+    );
+    let (trigger, shutdown) = crate::server::shutdown::channel();
+    delivery.deliver(&webhook, &event, shutdown.clone()).await;
+
+    let received = received.lock().unwrap().clone();
+    assert_eq!(received.len(), 1);
+    let (signature, body) = &received[0];
+    let timestamp: i64 = signature[2..signature.find(',').unwrap()].parse().unwrap();
+    assert_eq!(signature, &sign("secret", timestamp, body));
+    assert!(body.contains(r#""type":"elevator_cleared""#));
+    assert!(delivery.dead_letters.recent().is_empty());
+
+    let unreachable = Webhook {
+        url: String::from("http://127.0.0.1:9/events"),
+        ..webhook
+    };
+    delivery
+        .deliver(&unreachable, &event, shutdown.clone())
+        .await;
+    let dead_letters = delivery.dead_letters.recent();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].attempts, 2);
+
+    // Hosts that aren't allowed are refused before anything is sent
+    let local = Webhook {
+        url: String::from("http://localhost:9/events"),
+        ..unreachable.clone()
+    };
+    delivery.deliver(&local, &event, shutdown.clone()).await;
+    assert_eq!(delivery.dead_letters.recent()[1].attempts, 1);
+
+    trigger.shutdown();
+    delivery.deliver(&unreachable, &event, shutdown).await;
+    let dead_letters = delivery.dead_letters.recent();
+    assert_eq!(dead_letters[2].attempts, 1);
+    assert!(dead_letters[2].error.starts_with("Server shut down"));
+}
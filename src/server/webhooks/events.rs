@@ -0,0 +1,274 @@
+use crate::client::{
+    apis::{
+        advisories::{
+            bsa::{r#type::BsaType, Bsa},
+            elev::Elev,
+        },
+        real_time_estimates::etd::{EtdEstimate, EtdResponse},
+    },
+    constants::{direction::Direction, station::Station},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Departures have to be this late, in seconds, before a webhook hears about them by default
+pub const DEFAULT_DELAY_THRESHOLD: u32 = 5 * 60;
+
+// Advisories for every station are posted under this instead of a station's abbreviation
+const SYSTEM_WIDE: &str = "BART";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    AdvisoryPosted,
+    ElevatorCleared,
+    DepartureDelayed,
+}
+
+impl EventKind {
+    pub fn to_code(&self) -> &'static str {
+        match self {
+            EventKind::AdvisoryPosted => "advisory_posted",
+            EventKind::ElevatorCleared => "elevator_cleared",
+            EventKind::DepartureDelayed => "departure_delayed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    AdvisoryPosted {
+        advisory: Bsa,
+    },
+    ElevatorCleared {
+        outage: Elev,
+    },
+    // The most delayed train from `station` to `destination` in `direction`
+    DepartureDelayed {
+        station: Station,
+        destination: Station,
+        direction: Direction,
+        delay_seconds: i32,
+        estimate: EtdEstimate,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::AdvisoryPosted { .. } => EventKind::AdvisoryPosted,
+            Event::ElevatorCleared { .. } => EventKind::ElevatorCleared,
+            Event::DepartureDelayed { .. } => EventKind::DepartureDelayed,
+        }
+    }
+}
+
+// Each part narrows down the events it applies to and leaves the others alone, e.g. a direction
+// only filters departures
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Filter {
+    // Every kind of event when empty
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    pub station: Option<Station>,
+    pub direction: Option<Direction>,
+    pub advisory_type: Option<BsaType>,
+    pub delay_threshold: Option<u32>,
+}
+
+impl Filter {
+    pub fn wants(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    fn at_station(&self, station: &str) -> bool {
+        match &self.station {
+            Some(filtered) => {
+                station.eq_ignore_ascii_case(SYSTEM_WIDE)
+                    || station.eq_ignore_ascii_case(filtered.to_abbr())
+            }
+            None => true,
+        }
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        if !self.wants(event.kind()) {
+            return false;
+        }
+        match event {
+            Event::AdvisoryPosted { advisory } => {
+                self.at_station(&advisory.station)
+                    && self
+                        .advisory_type
+                        .as_ref()
+                        .map_or(true, |filtered| advisory.r#type.as_ref() == Some(filtered))
+            }
+            Event::ElevatorCleared { outage } => self.at_station(&outage.station),
+            Event::DepartureDelayed {
+                station,
+                direction,
+                delay_seconds,
+                ..
+            } => {
+                let threshold = self.delay_threshold.unwrap_or(DEFAULT_DELAY_THRESHOLD);
+                self.station
+                    .as_ref()
+                    .map_or(true, |filtered| filtered == station)
+                    && self
+                        .direction
+                        .as_ref()
+                        .map_or(true, |filtered| filtered == direction)
+                    && i64::from(*delay_seconds) > i64::from(threshold)
+            }
+        }
+    }
+}
+
+// Turns the pollers' snapshots into events. The first snapshot of each only sets the baseline, so
+// a restart doesn't announce every advisory that is already active.
+#[derive(Debug, Default)]
+pub struct Detector {
+    advisories: Option<HashSet<String>>,
+    outages: Option<HashMap<String, Elev>>,
+}
+
+impl Detector {
+    // Forgets the baseline of anything no longer followed, since it goes stale while nobody polls
+    // for it. What is still followed keeps its baseline across subscribing again.
+    pub fn keep(&mut self, wanted: &HashSet<EventKind>) {
+        if !wanted.contains(&EventKind::AdvisoryPosted) {
+            self.advisories = None;
+        }
+        if !wanted.contains(&EventKind::ElevatorCleared) {
+            self.outages = None;
+        }
+    }
+
+    // BART answers with a single placeholder entry without an id when nothing is going on
+    pub fn advisories(&mut self, advisories: &[Bsa]) -> Vec<Event> {
+        let current: HashSet<String> = advisories.iter().filter_map(|bsa| bsa.id.clone()).collect();
+        let events = match &self.advisories {
+            Some(previous) => advisories
+                .iter()
+                .filter(|bsa| bsa.id.as_ref().map_or(false, |id| !previous.contains(id)))
+                .map(|bsa| Event::AdvisoryPosted {
+                    advisory: bsa.clone(),
+                })
+                .collect(),
+            None => vec![],
+        };
+        self.advisories = Some(current);
+        events
+    }
+
+    pub fn elevators(&mut self, outages: &[Elev]) -> Vec<Event> {
+        let current: HashMap<String, Elev> = outages
+            .iter()
+            .filter_map(|elev| elev.id.clone().map(|id| (id, elev.clone())))
+            .collect();
+        let events = match &self.outages {
+            Some(previous) => previous
+                .iter()
+                .filter(|(id, _)| !current.contains_key(*id))
+                .map(|(_, elev)| Event::ElevatorCleared {
+                    outage: elev.clone(),
+                })
+                .collect(),
+            None => vec![],
+        };
+        self.outages = Some(current);
+        events
+    }
+}
+
+// Every delayed route, whether or not it was already delayed. Webhooks each remember which ones
+// they were told about, since how late counts as delayed is up to each of them.
+pub fn departures(response: &EtdResponse) -> Vec<Event> {
+    let mut events = vec![];
+    for station in &response.station {
+        for etd in &station.etd {
+            let mut latest: HashMap<&Direction, &EtdEstimate> = HashMap::new();
+            for estimate in etd.estimate.iter().filter(|estimate| estimate.delay > 0) {
+                let later = latest
+                    .get(&estimate.direction)
+                    .map_or(true, |current| estimate.delay > current.delay);
+                if later {
+                    latest.insert(&estimate.direction, estimate);
+                }
+            }
+            events.extend(latest.into_iter().map(|(direction, estimate)| {
+                Event::DepartureDelayed {
+                    station: station.abbr.clone(),
+                    destination: etd.abbreviation.clone(),
+                    direction: direction.clone(),
+                    delay_seconds: estimate.delay,
+                    estimate: estimate.clone(),
+                }
+            }));
+        }
+    }
+    events
+}
+
+#[test]
+fn detector_reports_changes_after_baseline() {
+    let bsa = |id: &str, station: &str| Bsa {
+        id: Some(String::from(id)),
+        station: String::from(station),
+        r#type: Some(BsaType::Delay),
+        description: String::from("Delays"),
+        sms_text: String::from("Delays"),
+        posted: None,
+        expires: None,
+    };
+    let elev = |id: &str| Elev {
+        id: Some(String::from(id)),
+        station: String::from("MONT"),
+        r#type: None,
+        description: String::from("Elevator out"),
+        sms_text: String::from("Elevator out"),
+        posted: None,
+        expires: None,
+    };
+
+    let mut detector = Detector::default();
+    assert!(detector.advisories(&[bsa("1", "BART")]).is_empty());
+    let posted = detector.advisories(&[bsa("1", "BART"), bsa("2", "EMBR")]);
+    assert_eq!(
+        posted,
+        vec![Event::AdvisoryPosted {
+            advisory: bsa("2", "EMBR")
+        }]
+    );
+
+    assert!(detector.elevators(&[elev("7")]).is_empty());
+    assert!(detector.elevators(&[elev("7")]).is_empty());
+    let cleared = detector.elevators(&[]);
+    assert_eq!(cleared, vec![Event::ElevatorCleared { outage: elev("7") }]);
+
+    let advisories: HashSet<EventKind> = vec![EventKind::AdvisoryPosted].into_iter().collect();
+    detector.keep(&advisories);
+    assert!(detector.elevators(&[elev("7")]).is_empty());
+    assert_eq!(
+        detector
+            .advisories(&[bsa("1", "BART"), bsa("3", "BART")])
+            .len(),
+        1
+    );
+
+    let filter = Filter {
+        station: Some(Station::MontgomerySt),
+        advisory_type: Some(BsaType::Emergency),
+        ..Filter::default()
+    };
+    assert!(filter.matches(&cleared[0]));
+    assert!(!filter.matches(&posted[0]));
+    let filter = Filter {
+        events: vec![EventKind::AdvisoryPosted],
+        ..Filter::default()
+    };
+    assert!(filter.matches(&posted[0]));
+    assert!(!filter.matches(&cleared[0]));
+}
@@ -0,0 +1,579 @@
+pub mod delivery;
+pub mod events;
+
+use self::{
+    delivery::{random_token, Delivery},
+    events::{Detector, Event, EventKind, Filter},
+};
+use crate::{
+    client::{
+        apis::{
+            advisories::{bsa::Bsa, elev::Elev},
+            real_time_estimates::etd::{EtdOptions, EtdResponse},
+        },
+        constants::{direction::Direction, station::Station},
+    },
+    server::{
+        auth::{Clients, API_KEY_HEADER},
+        poller::Registry,
+        rest::ErrorResponse,
+        shutdown::Shutdown,
+        Routes,
+    },
+};
+use anyhow::{anyhow, Result};
+use futures::{
+    channel::oneshot,
+    future, pin_mut,
+    stream::{self, BoxStream, StreamExt},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    net::{IpAddr, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+};
+use tokio::sync::watch;
+use url::{Host, Url};
+use warp::{filters::BoxedFilter, http::StatusCode, Filter as _, Reply};
+
+// Registrations are small, anything bigger isn't one
+const MAX_REGISTRATION_BYTES: u64 = 16 * 1024;
+const SECRET_LENGTH: usize = 32;
+const ID_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Registration {
+    pub url: String,
+    // Generated when left out
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub filter: Filter,
+}
+
+// The secret is only ever shown once, in the response to the registration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    // The client that registered it, `None` when the server is open to everyone
+    pub client: Option<String>,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub filter: Filter,
+}
+
+#[derive(Serialize)]
+struct Registered<'a> {
+    #[serde(flatten)]
+    webhook: &'a Webhook,
+    secret: &'a str,
+}
+
+fn internal(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local, fc00::/7, and link-local, fe80::/10
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+                || ip.to_ipv4().map_or(false, |ip| internal(&IpAddr::V4(ip)))
+        }
+    }
+}
+
+// The resolver blocks, so lookups run on a thread of their own rather than holding up the runtime
+async fn resolve(domain: String, port: u16) -> Result<Vec<IpAddr>> {
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        let addresses = (domain.as_str(), port)
+            .to_socket_addrs()
+            .map(|addresses| addresses.map(|address| address.ip()).collect());
+        let _ = sender.send(addresses);
+    });
+    receiver
+        .await
+        .map_err(|_| anyhow!("`url` host lookup was cancelled"))?
+        .map_err(|error| anyhow!("`url` host could not be resolved: {}", error))
+}
+
+// Keeps webhooks from reaching into the network the server runs in, unless the operator allows
+// the host. Names are resolved when the webhook is registered and again before every delivery.
+async fn check_target(url: &Url, allowed_hosts: &[String]) -> Result<()> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("`url` has no host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase();
+    if allowed_hosts.contains(&host) {
+        return Ok(());
+    }
+    let addresses: Vec<IpAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        Some(Host::Domain(domain)) => {
+            resolve(
+                String::from(domain),
+                url.port_or_known_default().unwrap_or(80),
+            )
+            .await?
+        }
+        None => vec![],
+    };
+    if host == "localhost" || host.ends_with(".localhost") || addresses.iter().any(internal) {
+        return Err(anyhow!(
+            "`url` is a loopback, private or link-local address, which the server doesn't allow"
+        ));
+    }
+    Ok(())
+}
+
+impl Webhook {
+    async fn new(
+        registration: Registration,
+        client: Option<String>,
+        allowed_hosts: &[String],
+    ) -> Result<Webhook> {
+        let url = Url::parse(&registration.url)
+            .map_err(|error| anyhow!("`url` is not a URL: {}", error))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(anyhow!("`url` must be http or https"));
+        }
+        check_target(&url, allowed_hosts).await?;
+        let secret = registration
+            .secret
+            .unwrap_or_else(|| random_token(SECRET_LENGTH));
+        if secret.is_empty() {
+            return Err(anyhow!("`secret` cannot be empty"));
+        }
+        Ok(Webhook {
+            id: random_token(ID_LENGTH),
+            client,
+            url: url.into_string(),
+            secret,
+            filter: registration.filter,
+        })
+    }
+}
+
+struct Hook {
+    webhook: Arc<Webhook>,
+    // Routes, as station, destination and direction, the webhook was last told are delayed
+    delayed: HashSet<(Station, Station, Direction)>,
+}
+
+// Webhooks are kept in memory, so they have to be registered again after a restart. Each client
+// only sees and removes its own.
+pub struct Webhooks {
+    hooks: Mutex<Vec<Hook>>,
+    delivery: Arc<Delivery>,
+    shutdown: Shutdown,
+    // The kinds of events any webhook can be sent
+    wanted: watch::Sender<HashSet<EventKind>>,
+    watching: watch::Receiver<HashSet<EventKind>>,
+}
+
+impl Webhooks {
+    // Deliveries still under way when the server stops waiting for them are dead-lettered
+    pub fn new(delivery: Delivery, shutdown: Shutdown) -> Webhooks {
+        let (wanted, watching) = watch::channel(HashSet::new());
+        let delivery = Arc::new(delivery);
+        let abandoned = delivery.clone();
+        shutdown.at_deadline(move || abandoned.abandon());
+        Webhooks {
+            hooks: Mutex::new(vec![]),
+            delivery,
+            shutdown,
+            wanted,
+            watching,
+        }
+    }
+
+    fn changed(&self, hooks: &[Hook]) {
+        let wanted = [
+            EventKind::AdvisoryPosted,
+            EventKind::ElevatorCleared,
+            EventKind::DepartureDelayed,
+        ]
+        .iter()
+        .filter(|kind| hooks.iter().any(|hook| hook.webhook.filter.wants(**kind)))
+        .cloned()
+        .collect();
+        // The receiver is kept in `self`, so this can't fail
+        let _ = self.wanted.broadcast(wanted);
+    }
+
+    pub async fn register(
+        &self,
+        registration: Registration,
+        client: Option<String>,
+    ) -> Result<Arc<Webhook>> {
+        let webhook = Webhook::new(registration, client, &self.delivery.allowed_hosts).await?;
+        let webhook = Arc::new(webhook);
+        let mut hooks = self.hooks.lock().unwrap();
+        hooks.push(Hook {
+            webhook: webhook.clone(),
+            delayed: HashSet::new(),
+        });
+        self.changed(&hooks);
+        Ok(webhook)
+    }
+
+    pub fn unregister(&self, id: &str, client: Option<&str>) -> bool {
+        let mut hooks = self.hooks.lock().unwrap();
+        let before = hooks.len();
+        hooks.retain(|hook| {
+            hook.webhook.id != id || hook.webhook.client.as_ref().map(String::as_str) != client
+        });
+        self.changed(&hooks);
+        hooks.len() != before
+    }
+
+    pub fn list(&self, client: Option<&str>) -> Vec<Arc<Webhook>> {
+        let hooks = self.hooks.lock().unwrap();
+        hooks
+            .iter()
+            .filter(|hook| hook.webhook.client.as_ref().map(String::as_str) == client)
+            .map(|hook| hook.webhook.clone())
+            .collect()
+    }
+
+    // Shutdown waits for deliveries like it does for connections
+    fn send(&self, webhook: Arc<Webhook>, event: Event) {
+        let delivery = self.delivery.clone();
+        let shutdown = self.shutdown.clone();
+        let live = shutdown.connection();
+        tokio::spawn(async move {
+            delivery.deliver(&webhook, &event, shutdown).await;
+            drop(live);
+        });
+    }
+
+    pub fn dispatch(&self, events: Vec<Event>) {
+        let hooks = self.hooks.lock().unwrap();
+        for hook in hooks.iter() {
+            for event in events
+                .iter()
+                .filter(|event| hook.webhook.filter.matches(event))
+            {
+                self.send(hook.webhook.clone(), event.clone());
+            }
+        }
+    }
+
+    // Only routes that weren't already delayed at the last snapshot are sent
+    pub fn dispatch_departures(&self, events: Vec<Event>) {
+        let mut hooks = self.hooks.lock().unwrap();
+        for hook in hooks.iter_mut() {
+            let mut delayed = HashSet::new();
+            for event in events
+                .iter()
+                .filter(|event| hook.webhook.filter.matches(event))
+            {
+                if let Event::DepartureDelayed {
+                    station,
+                    destination,
+                    direction,
+                    ..
+                } = event
+                {
+                    let route = (station.clone(), destination.clone(), direction.clone());
+                    if !hook.delayed.contains(&route) {
+                        self.send(hook.webhook.clone(), event.clone());
+                    }
+                    delayed.insert(route);
+                }
+            }
+            hook.delayed = delayed;
+        }
+    }
+
+    // Resolves with the kinds of events wanted once they are no longer `wanted`
+    async fn until_changed(&self, wanted: HashSet<EventKind>) -> HashSet<EventKind> {
+        let mut watching = self.watching.clone();
+        loop {
+            if *watching.get_ref() != wanted {
+                return watching.get_ref().clone();
+            }
+            // The sender is kept in `self`, so this can't end
+            if watching.recv().await.is_none() {
+                future::pending::<()>().await;
+            }
+        }
+    }
+
+    pub fn dead_letters(&self, client: Option<&str>) -> Vec<delivery::DeadLetter> {
+        self.delivery
+            .dead_letters
+            .recent()
+            .into_iter()
+            .filter(|letter| letter.client.as_ref().map(String::as_str) == client)
+            .collect()
+    }
+}
+
+enum Update {
+    Advisories(Vec<Bsa>),
+    Elevators(Vec<Elev>),
+    Departures(EtdResponse),
+}
+
+// Subscribes to the pollers the `wanted` events come from, until the subscriptions are dropped
+async fn follow(
+    webhooks: &Webhooks,
+    pollers: &Registry,
+    wanted: &HashSet<EventKind>,
+    detector: &mut Detector,
+) {
+    let advisories: BoxStream<'static, Update> = if wanted.contains(&EventKind::AdvisoryPosted) {
+        pollers
+            .bsa()
+            .map(|response| Update::Advisories(response.bsa))
+            .boxed()
+    } else {
+        stream::empty().boxed()
+    };
+    let elevators: BoxStream<'static, Update> = if wanted.contains(&EventKind::ElevatorCleared) {
+        pollers
+            .elev()
+            .map(|response| Update::Elevators(response.bsa))
+            .boxed()
+    } else {
+        stream::empty().boxed()
+    };
+    let departures: BoxStream<'static, Update> = if wanted.contains(&EventKind::DepartureDelayed) {
+        pollers
+            .etd(EtdOptions::OriginAll)
+            .map(Update::Departures)
+            .boxed()
+    } else {
+        stream::empty().boxed()
+    };
+
+    stream::select(advisories, stream::select(elevators, departures))
+        .for_each(|update| {
+            match update {
+                Update::Advisories(advisories) => {
+                    webhooks.dispatch(detector.advisories(&advisories))
+                }
+                Update::Elevators(outages) => webhooks.dispatch(detector.elevators(&outages)),
+                Update::Departures(response) => {
+                    webhooks.dispatch_departures(events::departures(&response))
+                }
+            }
+            future::ready(())
+        })
+        .await;
+}
+
+// Polls BART only for the kinds of events some webhook wants, subscribing again whenever that
+// changes, and stops on shutdown. Events are detected against the same baseline throughout.
+pub async fn run(webhooks: Arc<Webhooks>, pollers: Arc<Registry>, shutdown: Shutdown) {
+    let mut wanted = HashSet::new();
+    let mut detector = Detector::default();
+    while !shutdown.is_shutdown() {
+        let changed = webhooks.until_changed(wanted.clone());
+        let stopped = shutdown.clone().wait();
+        pin_mut!(changed, stopped);
+        wanted = match future::select(changed, stopped).await {
+            future::Either::Left((wanted, _)) => wanted,
+            future::Either::Right(_) => return,
+        };
+        detector.keep(&wanted);
+        if wanted.is_empty() {
+            continue;
+        }
+
+        let following = follow(&webhooks, &pollers, &wanted, &mut detector);
+        let changed = webhooks.until_changed(wanted.clone());
+        let stopped = shutdown.clone().wait();
+        pin_mut!(following, changed, stopped);
+        future::select(following, future::select(changed, stopped)).await;
+    }
+}
+
+fn error_reply(status: StatusCode, error: String) -> Box<dyn Reply> {
+    Box::new(warp::reply::with_status(
+        warp::reply::json(&ErrorResponse { error }),
+        status,
+    ))
+}
+
+// The name of the client making the request. Requests from unknown clients were already turned
+// away by `protect`.
+fn owner(clients: Arc<Clients>) -> BoxedFilter<(Option<String>,)> {
+    warp::header::optional::<String>(API_KEY_HEADER)
+        .and_then(move |key: Option<String>| {
+            let client = clients.authenticate(key.as_ref().map(String::as_str));
+            async move {
+                client
+                    .map(|client| client.map(|client| String::from(client.name())))
+                    .map_err(|_| warp::reject::not_found())
+            }
+        })
+        .boxed()
+}
+
+pub fn routes(webhooks: Arc<Webhooks>, clients: Arc<Clients>) -> Routes {
+    let webhooks = warp::any().map(move || webhooks.clone());
+    let owner = owner(clients);
+    let base = warp::path("webhooks");
+
+    let register = base
+        .and(warp::path::end())
+        .and(warp::post2())
+        .and(warp::body::content_length_limit(MAX_REGISTRATION_BYTES))
+        .and(warp::body::json())
+        .and(owner.clone())
+        .and(webhooks.clone())
+        .and_then(
+            |registration: Registration, owner: Option<String>, webhooks: Arc<Webhooks>| {
+                async move {
+                    let reply = match webhooks.register(registration, owner).await {
+                        Ok(webhook) => Box::new(warp::reply::with_status(
+                            warp::reply::json(&Registered {
+                                webhook: &webhook,
+                                secret: &webhook.secret,
+                            }),
+                            StatusCode::CREATED,
+                        )) as Box<dyn Reply>,
+                        Err(error) => {
+                            error_reply(StatusCode::BAD_REQUEST, format!("{:#}", error))
+                        }
+                    };
+                    Ok::<_, warp::Rejection>(reply)
+                }
+            },
+        );
+
+    let list = base
+        .and(warp::path::end())
+        .and(warp::get2())
+        .and(owner.clone())
+        .and(webhooks.clone())
+        .map(|owner: Option<String>, webhooks: Arc<Webhooks>| {
+            let list: Vec<Webhook> = webhooks
+                .list(owner.as_ref().map(String::as_str))
+                .iter()
+                .map(|webhook| (**webhook).clone())
+                .collect();
+            Box::new(warp::reply::json(&list)) as Box<dyn Reply>
+        });
+
+    let dead_letters = base
+        .and(warp::path("dead-letters"))
+        .and(warp::path::end())
+        .and(warp::get2())
+        .and(owner.clone())
+        .and(webhooks.clone())
+        .map(|owner: Option<String>, webhooks: Arc<Webhooks>| {
+            let dead_letters = webhooks.dead_letters(owner.as_ref().map(String::as_str));
+            Box::new(warp::reply::json(&dead_letters)) as Box<dyn Reply>
+        });
+
+    let unregister = base
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::delete2())
+        .and(owner)
+        .and(webhooks)
+        .map(
+            |id: String, owner: Option<String>, webhooks: Arc<Webhooks>| {
+                if webhooks.unregister(&id, owner.as_ref().map(String::as_str)) {
+                    Box::new(StatusCode::NO_CONTENT) as Box<dyn Reply>
+                } else {
+                    error_reply(StatusCode::NOT_FOUND, String::from("Unknown webhook"))
+                }
+            },
+        );
+
+    register
+        .or(list)
+        .unify()
+        .or(dead_letters)
+        .unify()
+        .or(unregister)
+        .unify()
+        .boxed()
+}
+
+#[tokio::test]
+async fn webhooks_are_owned_and_kept_out_of_local_networks() {
+    let delivery = Delivery::new(
+        delivery::retry_policy(0),
+        delivery::DeadLetters::new(None),
+        vec![String::from("127.0.0.1")],
+    );
+    let (_trigger, shutdown) = crate::server::shutdown::channel();
+    let webhooks = Webhooks::new(delivery, shutdown);
+    let registration = |url: &str, events: Vec<EventKind>| Registration {
+        url: String::from(url),
+        secret: None,
+        filter: Filter {
+            events,
+            ..Filter::default()
+        },
+    };
+    let owner = Some(String::from("partner"));
+
+    for url in &[
+        "http://10.1.2.3/events",
+        "http://192.168.0.1/events",
+        "http://169.254.169.254/latest",
+        "http://[::1]/events",
+        "http://[fe80::1]/events",
+        "http://localhost/events",
+    ] {
+        assert!(webhooks
+            .register(registration(url, vec![]), None)
+            .await
+            .is_err());
+    }
+    assert!(webhooks
+        .register(registration("http://127.0.0.1:8080/events", vec![]), None)
+        .await
+        .is_ok());
+
+    let webhook = webhooks
+        .register(
+            registration(
+                "https://93.184.216.34/events",
+                vec![EventKind::AdvisoryPosted],
+            ),
+            owner.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(webhooks.list(Some("partner")), vec![webhook.clone()]);
+    assert!(webhooks.list(Some("other")).is_empty());
+    assert!(!webhooks.unregister(&webhook.id, Some("other")));
+    assert!(!webhooks.unregister(&webhook.id, None));
+
+    let everything: HashSet<EventKind> = [
+        EventKind::AdvisoryPosted,
+        EventKind::ElevatorCleared,
+        EventKind::DepartureDelayed,
+    ]
+    .iter()
+    .cloned()
+    .collect();
+    assert_eq!(*webhooks.watching.get_ref(), everything);
+    let id = webhooks.list(None)[0].id.clone();
+    assert!(webhooks.unregister(&id, None));
+    let advisories: HashSet<EventKind> = vec![EventKind::AdvisoryPosted].into_iter().collect();
+    assert_eq!(*webhooks.watching.get_ref(), advisories);
+    assert!(webhooks.unregister(&webhook.id, owner.as_ref().map(String::as_str)));
+    assert!(webhooks.watching.get_ref().is_empty());
+}